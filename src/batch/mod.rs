@@ -0,0 +1,135 @@
+//! Looking up lots of addresses at once, a few at a time.
+
+mod resume;
+
+pub use resume::{CsvBatch, CsvBatchError, CsvBatchSummary};
+
+use crate::{Client, TaxInfo, TaxInfoError};
+use futures::stream::{self, Stream, StreamExt};
+
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// One address to look up as part of a batch
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct AddressQuery {
+    pub addr: String,
+    pub city: String,
+    pub zip: String,
+}
+
+impl AddressQuery {
+    pub fn new(addr: impl Into<String>, city: impl Into<String>, zip: impl Into<String>) -> Self {
+        AddressQuery {
+            addr: addr.into(),
+            city: city.into(),
+            zip: zip.into(),
+        }
+    }
+}
+
+impl<A, C, Z> From<(A, C, Z)> for AddressQuery
+where
+    A: Into<String>,
+    C: Into<String>,
+    Z: Into<String>,
+{
+    fn from((addr, city, zip): (A, C, Z)) -> Self {
+        Self::new(addr, city, zip)
+    }
+}
+
+/// How far along a batch is
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Progress {
+    /// Lookups that have finished, successfully or not
+    pub completed: usize,
+    /// Of the completed lookups, how many were errors
+    pub failed: usize,
+    pub total: usize,
+}
+
+/// A batch of lookups, made with [`Client::batch`]. Each lookup goes through [`Client::get`], so
+/// gets the client's retries and cache.
+pub struct Batch<'a> {
+    client: &'a Client,
+    queries: Vec<AddressQuery>,
+    concurrency: usize,
+    on_progress: Option<Box<dyn FnMut(Progress) + Send + 'a>>,
+}
+
+impl<'a> Batch<'a> {
+    pub(crate) fn new(client: &'a Client, queries: Vec<AddressQuery>) -> Self {
+        Batch {
+            client,
+            queries,
+            concurrency: DEFAULT_CONCURRENCY,
+            on_progress: None,
+        }
+    }
+
+    /// How many requests to DOR can be waiting on an answer at the same time. Defaults to 4,
+    /// anything below 1 is taken as 1. Results still come back in the order of the queries.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Called every time a lookup finishes
+    pub fn on_progress(mut self, on_progress: impl FnMut(Progress) + Send + 'a) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    /// Runs every lookup, answering in the same order as the queries
+    pub async fn run(self) -> Vec<Result<TaxInfo, TaxInfoError>> {
+        let total = self.queries.len();
+        let mut results: Vec<Option<Result<TaxInfo, TaxInfoError>>> = Vec::with_capacity(total);
+        results.resize_with(total, || None);
+
+        let mut on_progress = self.on_progress;
+        let mut progress = Progress { completed: 0, failed: 0, total };
+        let lookups = lookups(self.client, self.queries, self.concurrency);
+        futures::pin_mut!(lookups);
+        while let Some((index, result)) = lookups.next().await {
+            progress.completed += 1;
+            if result.is_err() {
+                progress.failed += 1;
+            }
+            if let Some(on_progress) = on_progress.as_mut() {
+                on_progress(progress);
+            }
+            results[index] = Some(result);
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.expect("every query gets a result"))
+            .collect()
+    }
+
+    /// Answers as lookups finish, which isn't necessarily query order, so each comes with the
+    /// index of its query. Progress isn't reported, count the items instead.
+    pub fn stream(self) -> impl Stream<Item = (usize, Result<TaxInfo, TaxInfoError>)> + 'a {
+        lookups(self.client, self.queries, self.concurrency)
+    }
+}
+
+fn lookups<'a>(
+    client: &'a Client,
+    queries: Vec<AddressQuery>,
+    concurrency: usize,
+) -> impl Stream<Item = (usize, Result<TaxInfo, TaxInfoError>)> + 'a {
+    stream::iter(queries.into_iter().enumerate())
+        .map(move |(index, query)| async move {
+            (index, client.get(&query.addr, &query.city, &query.zip).await)
+        })
+        .buffer_unordered(concurrency)
+}
@@ -0,0 +1,373 @@
+//! Batches that read and write csv files, and pick up where they left off.
+
+use super::{lookups, AddressQuery, Progress};
+use crate::cache::CacheKey;
+use crate::{Client, TaxInfo, TaxInfoError};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Added after the input's own columns in the output
+const OUTPUT_COLUMNS: [&str; 6] =
+    ["loccode", "rate", "localrate", "code", "normalized_addr", "error"];
+
+/// The row, how it went, the normalized address, city and zip it was looked up with, then the
+/// output columns
+const CHECKPOINT_HEADER: [&str; 11] = [
+    "row", "status", "addr", "city", "zip", "loccode", "rate", "localrate", "code",
+    "normalized_addr", "error",
+];
+
+/// Error running a [`CsvBatch`]. Lookups that fail aren't errors, they're rows in the output.
+#[derive(Debug)]
+pub enum CsvBatchError {
+    Io(io::Error),
+    Csv(csv::Error),
+    /// The input has no column with this name
+    MissingColumn(String),
+}
+
+impl fmt::Display for CsvBatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvBatchError::Io(e) => write!(f, "{}", e),
+            CsvBatchError::Csv(e) => write!(f, "bad csv: {}", e),
+            CsvBatchError::MissingColumn(name) => write!(f, "the input has no {:?} column", name),
+        }
+    }
+}
+
+impl std::error::Error for CsvBatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CsvBatchError::Io(e) => Some(e),
+            CsvBatchError::Csv(e) => Some(e),
+            CsvBatchError::MissingColumn(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for CsvBatchError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<csv::Error> for CsvBatchError {
+    fn from(e: csv::Error) -> Self {
+        Self::Csv(e)
+    }
+}
+
+/// How a [`CsvBatch`] went, counting rows done in earlier runs too
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct CsvBatchSummary {
+    /// Rows in the input
+    pub total: usize,
+    /// Rows already done by an earlier run, and not looked up again
+    pub skipped: usize,
+    /// Rows DOR had a rate for
+    pub succeeded: usize,
+    /// Rows DOR answered with an error code, like an address it couldn't find. Looking them up
+    /// again won't help, so reruns skip them.
+    pub rejected: usize,
+    /// Rows that didn't get an answer, reruns try these again
+    pub failed: usize,
+}
+
+/// Looks up every row of a csv file, writing each row back out with its rates.
+///
+/// Progress goes to a checkpoint file as each lookup finishes, by default the output path with
+/// `.checkpoint` on the end. Running the same batch again skips the rows that are done, and only
+/// looks up the ones that failed or never ran, so a batch that died half way through doesn't
+/// start over. The output is written once all the lookups are done, with every row of the input
+/// in order, and failures left blank with the error.
+///
+/// Rows are matched to the checkpoint by position, and a row whose address, city or zip changed
+/// since is looked up again.
+///
+/// ```no_run
+/// # async fn example(client: wataxrate::Client) -> Result<(), wataxrate::batch::CsvBatchError> {
+/// let summary = client
+///     .csv_batch("orders.csv", "orders-with-rates.csv")
+///     .columns("Ship Street", "Ship City", "Ship Zip")
+///     .run()
+///     .await?;
+/// println!("{} rows still need a rerun", summary.failed);
+/// # Ok(())
+/// # }
+/// ```
+pub struct CsvBatch<'a> {
+    client: &'a Client,
+    input: PathBuf,
+    output: PathBuf,
+    checkpoint: PathBuf,
+    columns: [String; 3],
+    concurrency: usize,
+    on_progress: Option<Box<dyn FnMut(Progress) + Send + 'a>>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Status {
+    Ok,
+    Rejected,
+    Failed,
+}
+
+/// What the checkpoint knows about a row
+#[derive(Clone, Debug)]
+struct Entry {
+    status: Status,
+    /// What the row was looked up with
+    key: CacheKey,
+    /// One for each of `OUTPUT_COLUMNS`
+    fields: Vec<String>,
+}
+
+impl<'a> CsvBatch<'a> {
+    pub(crate) fn new(client: &'a Client, input: PathBuf, output: PathBuf) -> Self {
+        let mut checkpoint: OsString = output.clone().into_os_string();
+        checkpoint.push(".checkpoint");
+        CsvBatch {
+            client,
+            input,
+            output,
+            checkpoint: checkpoint.into(),
+            columns: ["addr".to_string(), "city".to_string(), "zip".to_string()],
+            concurrency: super::DEFAULT_CONCURRENCY,
+            on_progress: None,
+        }
+    }
+
+    /// The names of the input's address, city and zip columns, ignoring case. Defaults to
+    /// `addr`, `city` and `zip`.
+    pub fn columns(
+        mut self,
+        addr: impl Into<String>,
+        city: impl Into<String>,
+        zip: impl Into<String>,
+    ) -> Self {
+        self.columns = [addr.into(), city.into(), zip.into()];
+        self
+    }
+
+    /// Where to keep track of progress
+    pub fn checkpoint(mut self, checkpoint: impl Into<PathBuf>) -> Self {
+        self.checkpoint = checkpoint.into();
+        self
+    }
+
+    /// How many rows are looked up at the same time. Defaults to 4. The checkpoint gets rows in
+    /// the order they finish, the output is always in input order.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Called every time a lookup finishes. Only counts the rows this run looks up.
+    pub fn on_progress(mut self, on_progress: impl FnMut(Progress) + Send + 'a) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Does whatever's left, and writes the output
+    pub async fn run(self) -> Result<CsvBatchSummary, CsvBatchError> {
+        let CsvBatch { client, input, output, checkpoint, columns, concurrency, mut on_progress } =
+            self;
+
+        let (headers, rows) = read_input(&input)?;
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| CsvBatchError::MissingColumn(name.to_string()))
+        };
+        let (addr, city, zip) = (column(&columns[0])?, column(&columns[1])?, column(&columns[2])?);
+        let field = |row: usize, col: usize| rows[row].get(col).unwrap_or("");
+        let key = |row: usize| CacheKey::new(field(row, addr), field(row, city), field(row, zip));
+
+        let mut entries = read_checkpoint(&checkpoint)?;
+        let todo: Vec<usize> = (0..rows.len())
+            .filter(|&row| match entries.get(&row) {
+                Some(entry) => entry.status == Status::Failed || entry.key != key(row),
+                None => true,
+            })
+            .collect();
+        debug!("{} of {} rows left to look up", todo.len(), rows.len());
+
+        let queries = todo
+            .iter()
+            .map(|&row| AddressQuery::new(field(row, addr), field(row, city), field(row, zip)))
+            .collect();
+        let mut writer = checkpoint_writer(&checkpoint)?;
+        let mut progress = Progress { completed: 0, failed: 0, total: todo.len() };
+
+        let lookups = lookups(client, queries, concurrency);
+        futures::pin_mut!(lookups);
+        while let Some((index, result)) = lookups.next().await {
+            let row = todo[index];
+            let entry = Entry::new(key(row), &result);
+
+            let mut record = vec![
+                row.to_string(),
+                entry.status.as_str().to_string(),
+                entry.key.addr().to_string(),
+                entry.key.city().to_string(),
+                entry.key.zip().to_string(),
+            ];
+            record.extend(entry.fields.iter().cloned());
+            writer.write_record(&record)?;
+            writer.flush()?;
+
+            progress.completed += 1;
+            if entry.status == Status::Failed {
+                progress.failed += 1;
+            }
+            if let Some(on_progress) = on_progress.as_mut() {
+                on_progress(progress);
+            }
+            entries.insert(row, entry);
+        }
+
+        write_output(&output, &headers, &rows, &entries)?;
+
+        let mut summary = CsvBatchSummary {
+            total: rows.len(),
+            skipped: rows.len() - todo.len(),
+            ..CsvBatchSummary::default()
+        };
+        for row in 0..rows.len() {
+            match entries.get(&row).map(|entry| entry.status) {
+                Some(Status::Ok) => summary.succeeded += 1,
+                Some(Status::Rejected) => summary.rejected += 1,
+                Some(Status::Failed) | None => summary.failed += 1,
+            }
+        }
+        Ok(summary)
+    }
+}
+
+impl Status {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Rejected => "rejected",
+            Status::Failed => "failed",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "ok" => Some(Status::Ok),
+            "rejected" => Some(Status::Rejected),
+            "failed" => Some(Status::Failed),
+            _ => None,
+        }
+    }
+}
+
+impl Entry {
+    fn new(key: CacheKey, result: &Result<TaxInfo, TaxInfoError>) -> Self {
+        let normalized = key.addr().to_string();
+        let rates = |info: &TaxInfo| {
+            vec![info.loccode.to_string(), info.rate.to_string(), info.localrate.to_string()]
+        };
+        let (status, mut fields) = match result {
+            Ok(info) => (Status::Ok, rates(info)),
+            Err(TaxInfoError::Dor(_)) | Err(TaxInfoError::InvalidLongLat(_)) => {
+                (Status::Rejected, vec![String::new(); 3])
+            }
+            Err(_) => (Status::Failed, vec![String::new(); 3]),
+        };
+        let code = match result {
+            Ok(info) | Err(TaxInfoError::Dor((_, info))) | Err(TaxInfoError::InvalidLongLat(info)) => {
+                info.code.number().to_string()
+            }
+            Err(_) => String::new(),
+        };
+        let error = result.as_ref().err().map(|e| e.to_string()).unwrap_or_default();
+        fields.extend(vec![code, normalized, error]);
+        Entry { status, key, fields }
+    }
+}
+
+fn read_input(path: &Path) -> Result<(csv::StringRecord, Vec<csv::StringRecord>), CsvBatchError> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let rows = reader.records().collect::<Result<Vec<_>, _>>()?;
+    Ok((headers, rows))
+}
+
+/// The latest entry for each row. Rows that can't be read, like one cut short by a crash, are
+/// skipped, those rows just get looked up again.
+fn read_checkpoint(path: &Path) -> Result<HashMap<usize, Entry>, CsvBatchError> {
+    let mut entries = HashMap::new();
+    if !path.exists() {
+        return Ok(entries);
+    }
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) if record.len() == CHECKPOINT_HEADER.len() => record,
+            _ => {
+                debug!("skipping unreadable checkpoint row in {}", path.display());
+                continue;
+            }
+        };
+        let row = record[0].parse::<usize>().ok();
+        let status = Status::parse(&record[1]);
+        if let (Some(row), Some(status)) = (row, status) {
+            // Already normalized, and normalizing again doesn't change them
+            let key = CacheKey::new(&record[2], &record[3], &record[4]);
+            let fields = record.iter().skip(5).map(str::to_string).collect();
+            entries.insert(row, Entry { status, key, fields });
+        }
+    }
+    Ok(entries)
+}
+
+/// Appends to the checkpoint, starting it with a header when it's new
+fn checkpoint_writer(path: &Path) -> Result<csv::Writer<File>, CsvBatchError> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let is_new = file.metadata()?.len() == 0;
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(file);
+    if is_new {
+        writer.write_record(&CHECKPOINT_HEADER)?;
+        writer.flush()?;
+    }
+    Ok(writer)
+}
+
+/// Written next to the output and moved over it, so there's never half an output file
+fn write_output(
+    path: &Path,
+    headers: &csv::StringRecord,
+    rows: &[csv::StringRecord],
+    entries: &HashMap<usize, Entry>,
+) -> Result<(), CsvBatchError> {
+    let tmp = path.with_extension("tmp");
+    let file = File::create(&tmp)?;
+    {
+        let mut writer = csv::Writer::from_writer(&file);
+        let mut header: Vec<&str> = headers.iter().collect();
+        header.extend(OUTPUT_COLUMNS.iter());
+        writer.write_record(&header)?;
+
+        let blank = vec![String::new(); OUTPUT_COLUMNS.len()];
+        for (row, record) in rows.iter().enumerate() {
+            let mut fields: Vec<&str> =
+                (0..headers.len()).map(|col| record.get(col).unwrap_or("")).collect();
+            let extra = entries.get(&row).map(|entry| &entry.fields).unwrap_or(&blank);
+            fields.extend(extra.iter().map(String::as_str));
+            writer.write_record(&fields)?;
+        }
+        writer.flush()?;
+    }
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
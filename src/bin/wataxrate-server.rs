@@ -0,0 +1,355 @@
+//! A small HTTP service that answers with JSON, for programs that would rather not talk to DOR's
+//! XML interface themselves. Needs the `server` feature.
+//!
+//! - `GET /rate?addr=&city=&zip=`, optionally with `&date=YYYY-MM-DD`
+//! - `GET /rate/coords?lat=&lng=`, optionally with `&date=YYYY-MM-DD`
+//! - `POST /rate/batch` with a JSON array of `{"addr", "city", "zip"}`, answers with an array in
+//!   the same order. At most 1000 addresses and 1 MiB per batch, more is a 413
+//! - `GET /healthz` answers 200 while the process is up
+//! - `GET /readyz` answers 503 when the last lookup couldn't reach DOR. While that's so it checks
+//!   on DOR itself, at most every 10 seconds, and answers 200 again once DOR does
+//!
+//! Rates come back as `TaxInfo` serialized with the `serde` feature, rates are strings so they
+//! stay exact.
+//!
+//! Lookups share one client, so they share its retries, cache and rate limit.
+
+use futures::StreamExt;
+use hyper::header::CONTENT_LENGTH;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wataxrate::batch::AddressQuery;
+use wataxrate::cache::MemoryCache;
+use wataxrate::{Client, Code, Date, RateLimiter, StopReason, TaxInfo, TaxInfoError};
+
+const USAGE: &str = "\
+usage: wataxrate-server [options]
+
+options:
+    --listen <addr:port>     where to listen, 127.0.0.1:8080 by default
+    --cache <entries>        how many lookups to cache, 10000 by default, 0 for none
+    --rate-limit <per sec>   most requests to DOR per second, 10 by default
+    --base-url <url>         somewhere other than DOR to send lookups";
+
+/// More than this in one batch is turned away, split it up instead
+const MAX_BATCH: usize = 1_000;
+/// A batch body bigger than this is turned away without reading the rest. `MAX_BATCH` addresses
+/// fit in it with plenty of room.
+const MAX_BATCH_BYTES: usize = 1024 * 1024;
+
+/// How often `/readyz` checks on DOR while it's unreachable
+const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+/// How long one of those checks may take
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+struct State {
+    client: Client,
+    /// False while DOR can't be reached
+    ready: AtomicBool,
+    /// When `/readyz` last checked on DOR
+    last_probe: Mutex<Option<Instant>>,
+}
+
+#[derive(Serialize)]
+struct ErrorJson {
+    /// Short and stable, for programs to branch on
+    error: &'static str,
+    /// For people
+    message: String,
+    /// When DOR answered with an error code
+    code: Option<Code>,
+}
+
+#[derive(Deserialize)]
+struct BatchItem {
+    addr: String,
+    city: String,
+    zip: String,
+}
+
+/// One answer in a batch, either a rate or an error
+#[derive(Serialize)]
+#[serde(untagged)]
+enum BatchResult<'a> {
+    Ok(&'a TaxInfo),
+    Err(ErrorJson),
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (listen, client) = match configure(&args) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            std::process::exit(64);
+        }
+    };
+
+    let state = Arc::new(State {
+        client,
+        ready: AtomicBool::new(true),
+        last_probe: Mutex::new(None),
+    });
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| handle(state.clone(), request)))
+        }
+    });
+
+    eprintln!("listening on http://{}", listen);
+    if let Err(e) = Server::bind(&listen).serve(make_service).await {
+        eprintln!("server error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn configure(args: &[String]) -> Result<(SocketAddr, Client), String> {
+    let mut flags = HashMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{}", USAGE);
+            std::process::exit(0);
+        }
+        if !arg.starts_with("--") {
+            return Err(format!("unexpected argument {}", arg));
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        flags.insert(arg[2..].to_string(), value.clone());
+    }
+    let flag = |name: &str| flags.get(name).map(String::as_str);
+
+    let listen = flag("listen")
+        .unwrap_or("127.0.0.1:8080")
+        .parse()
+        .map_err(|_| "--listen is not an address and port".to_string())?;
+    let cache: usize = flag("cache")
+        .unwrap_or("10000")
+        .parse()
+        .map_err(|_| "--cache is not a number".to_string())?;
+    let per_second: f64 = flag("rate-limit")
+        .unwrap_or("10")
+        .parse()
+        .ok()
+        .filter(|n: &f64| *n > 0.0)
+        .ok_or_else(|| "--rate-limit is not a positive number".to_string())?;
+
+    let rate_limiter = RateLimiter::new(per_second, per_second.ceil() as u32);
+    let mut builder = Client::builder().rate_limiter(rate_limiter);
+    if cache > 0 {
+        builder = builder.cache(MemoryCache::new(cache));
+    }
+    if let Some(base_url) = flag("base-url") {
+        builder = builder.base_url(base_url);
+    }
+    let client = builder.build().map_err(|e| e.to_string())?;
+    Ok((listen, client))
+}
+
+async fn handle(state: Arc<State>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let query = query(&request);
+    let param = |name: &str| query.get(name).map(String::as_str).unwrap_or("");
+
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/healthz") => json(StatusCode::OK, &"ok"),
+        (&Method::GET, "/readyz") => {
+            if !state.ready.load(Ordering::Relaxed) {
+                probe(&state).await;
+            }
+            if state.ready.load(Ordering::Relaxed) {
+                json(StatusCode::OK, &"ready")
+            } else {
+                json(StatusCode::SERVICE_UNAVAILABLE, &"can't reach DOR")
+            }
+        }
+        (&Method::GET, "/rate") => match date(&query) {
+            Ok(date) => {
+                let (addr, city, zip) = (param("addr"), param("city"), param("zip"));
+                if addr.is_empty() || zip.is_empty() {
+                    bad_request("addr and zip are required")
+                } else {
+                    let result = match date {
+                        Some(date) => state.client.get_as_of(addr, city, zip, date).await,
+                        None => state.client.get(addr, city, zip).await,
+                    };
+                    answer(&state, &result)
+                }
+            }
+            Err(message) => bad_request(message),
+        },
+        (&Method::GET, "/rate/coords") => {
+            let lat = param("lat").parse::<f64>();
+            let lng = param("lng").parse::<f64>();
+            match (lat, lng, date(&query)) {
+                (Ok(lat), Ok(lng), Ok(date)) => {
+                    let result = match date {
+                        Some(date) => state.client.get_by_coords_as_of(lat, lng, date).await,
+                        None => state.client.get_by_coords(lat, lng).await,
+                    };
+                    answer(&state, &result)
+                }
+                (_, _, Err(message)) => bad_request(message),
+                _ => bad_request("lat and lng must be numbers"),
+            }
+        }
+        (&Method::POST, "/rate/batch") => batch(&state, request).await,
+        _ => {
+            let body = error_json("not_found", "no such endpoint".to_string(), None);
+            json(StatusCode::NOT_FOUND, &body)
+        }
+    };
+    Ok(response)
+}
+
+async fn batch(state: &State, request: Request<Body>) -> Response<Body> {
+    let too_large = || {
+        let message = format!("at most {} bytes per batch", MAX_BATCH_BYTES);
+        json(StatusCode::PAYLOAD_TOO_LARGE, &error_json("too_large", message, None))
+    };
+    let length = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<usize>().ok());
+    if matches!(length, Some(length) if length > MAX_BATCH_BYTES) {
+        return too_large();
+    }
+
+    // Content-Length can be missing or wrong, so count while reading too
+    let mut chunks = request.into_body();
+    let mut body = Vec::new();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(_) => return bad_request("couldn't read the body"),
+        };
+        if body.len() + chunk.len() > MAX_BATCH_BYTES {
+            return too_large();
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let items: Vec<BatchItem> = match serde_json::from_slice(&body) {
+        Ok(items) => items,
+        Err(_) => return bad_request("body should be a JSON array of {\"addr\", \"city\", \"zip\"}"),
+    };
+    if items.len() > MAX_BATCH {
+        let message = format!("at most {} addresses per batch", MAX_BATCH);
+        return json(StatusCode::PAYLOAD_TOO_LARGE, &error_json("too_many", message, None));
+    }
+
+    let queries = items.into_iter().map(|item| AddressQuery::new(item.addr, item.city, item.zip));
+    let results = state.client.batch(queries).run().await;
+    let answers: Vec<BatchResult> = results
+        .iter()
+        .map(|result| {
+            track_readiness(state, result);
+            match result {
+                Ok(info) => BatchResult::Ok(info),
+                Err(e) => BatchResult::Err(error_body(e).1),
+            }
+        })
+        .collect();
+    json(StatusCode::OK, &answers)
+}
+
+/// A lookup's result as a response
+fn answer(state: &State, result: &Result<TaxInfo, TaxInfoError>) -> Response<Body> {
+    track_readiness(state, result);
+    match result {
+        Ok(info) => json(StatusCode::OK, info),
+        Err(e) => {
+            let (status, body) = error_body(e);
+            json(status, &body)
+        }
+    }
+}
+
+/// Not ready after failing to reach DOR, ready again once it answers
+fn track_readiness(state: &State, result: &Result<TaxInfo, TaxInfoError>) {
+    let unreachable = match result {
+        Ok(_) => false,
+        Err(e) => error_body(e).0.is_server_error() && !matches!(e, TaxInfoError::Internal(_)),
+    };
+    state.ready.store(!unreachable, Ordering::Relaxed);
+}
+
+/// Once `/readyz` fails, load balancers stop sending lookups, so nothing else would find out DOR
+/// is back. Looks up the Space Needle to check, at most every `PROBE_INTERVAL`, through the
+/// client's rate limiter like any other lookup.
+async fn probe(state: &State) {
+    {
+        let mut last_probe = state.last_probe.lock().unwrap();
+        if matches!(*last_probe, Some(at) if at.elapsed() < PROBE_INTERVAL) {
+            return;
+        }
+        *last_probe = Some(Instant::now());
+    }
+    let lookup = state.client.get_basic("400 Broad St", "Seattle", "98109");
+    if let Ok(result) = tokio::time::timeout(PROBE_TIMEOUT, lookup).await {
+        track_readiness(state, &result);
+    }
+}
+
+fn error_body(e: &TaxInfoError) -> (StatusCode, ErrorJson) {
+    let (status, error) = match e {
+        TaxInfoError::Dor((Code::NoAddrNoZips, _)) => (StatusCode::NOT_FOUND, "not_found"),
+        TaxInfoError::Dor((Code::InternalError, _)) => (StatusCode::BAD_GATEWAY, "dor_error"),
+        TaxInfoError::Dor(_) => (StatusCode::UNPROCESSABLE_ENTITY, "dor_error"),
+        TaxInfoError::InvalidLongLat(_) => (StatusCode::UNPROCESSABLE_ENTITY, "invalid_coordinates"),
+        TaxInfoError::OutsideWashington(_) => (StatusCode::UNPROCESSABLE_ENTITY, "outside_washington"),
+        TaxInfoError::Timeout(_) => (StatusCode::GATEWAY_TIMEOUT, "timeout"),
+        TaxInfoError::NoMoreRetries(report) if report.reason == StopReason::Deadline => {
+            (StatusCode::GATEWAY_TIMEOUT, "timeout")
+        }
+        TaxInfoError::NotXml(_) | TaxInfoError::Schema { .. } => {
+            (StatusCode::BAD_GATEWAY, "bad_dor_response")
+        }
+        TaxInfoError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        _ => (StatusCode::BAD_GATEWAY, "dor_unavailable"),
+    };
+    let code = match e {
+        TaxInfoError::Dor((code, _)) => Some(*code),
+        TaxInfoError::InvalidLongLat(info) => Some(info.code),
+        _ => None,
+    };
+    (status, error_json(error, e.to_string(), code))
+}
+
+fn error_json(error: &'static str, message: String, code: Option<Code>) -> ErrorJson {
+    ErrorJson { error, message, code }
+}
+
+fn bad_request(message: &str) -> Response<Body> {
+    json(StatusCode::BAD_REQUEST, &error_json("bad_request", message.to_string(), None))
+}
+
+fn json<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    let body = serde_json::to_vec(body).expect("responses always serialize");
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body))
+        .expect("responses are always valid")
+}
+
+fn query(request: &Request<Body>) -> HashMap<String, String> {
+    let query = request.uri().query().unwrap_or("");
+    url::form_urlencoded::parse(query.as_bytes()).into_owned().collect()
+}
+
+/// The optional `date` parameter
+fn date(query: &HashMap<String, String>) -> Result<Option<Date>, &'static str> {
+    match query.get("date") {
+        Some(date) if !date.is_empty() => date.parse().map(Some),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,348 @@
+//! Looks up WA tax rates from the command line. Needs the `cli` feature.
+//!
+//! Exit codes, so scripts can branch on them:
+//! - 0: DOR found the address
+//! - 1 to 5: DOR answered, but matched less exactly, the number is DOR's code
+//! - 6: DOR found neither the address nor the zip
+//! - 7: DOR said the latitude/longitude was invalid
+//! - 9: DOR had an internal error
+//! - 64: bad arguments
+//! - 65: bad input, like a point outside WA or an unreadable csv row
+//! - 69: couldn't get an answer from DOR, after retries
+//! - 74: couldn't read or write a file
+//! - 76: DOR's answer didn't make sense
+//!
+//! `batch` exits with the worst code of any row.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::process;
+use wataxrate::batch::AddressQuery;
+use wataxrate::blocking::Client;
+use wataxrate::{Code, Date, Decimal, TaxInfo, TaxInfoError};
+
+const USAGE: &str = "\
+usage:
+    wataxrate lookup --addr <addr> --city <city> --zip <zip> [options]
+    wataxrate coords --lat <lat> --lng <lng> [options]
+    wataxrate batch <file.csv> [--concurrency <n>] [options]
+
+options:
+    --format <table|json|csv>   how to print results, table by default
+    --date <YYYY-MM-DD>         rates that applied on this date instead of today
+    --base-url <url>            somewhere other than DOR to send lookups
+
+batch reads a csv with addr, city and zip columns.
+
+exit codes:
+    0-9   DOR's code, 0 when the address was found, 6 and up when DOR had no rate
+    64    bad arguments
+    65    bad input, like a point outside WA
+    69    no answer from DOR
+    74    couldn't read or write a file
+    76    DOR's answer didn't make sense
+batch exits with the worst code of any row.";
+
+const EXIT_USAGE: i32 = 64;
+const EXIT_DATA: i32 = 65;
+const EXIT_UNAVAILABLE: i32 = 69;
+const EXIT_IO: i32 = 74;
+const EXIT_PROTOCOL: i32 = 76;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+/// One line of output, flattened from a lookup.
+///
+/// As JSON it's serialized like `wataxrate-server` serializes `TaxInfo`: `code` has its name
+/// and number, rates are strings so they stay exact, and what's missing is null.
+#[derive(Serialize)]
+struct Row {
+    addr: String,
+    city: String,
+    zip: String,
+    code: Option<Code>,
+    /// Already in `code` for JSON
+    #[serde(skip)]
+    code_name: Option<&'static str>,
+    loccode: Option<i32>,
+    name: Option<String>,
+    rate: Option<Decimal>,
+    localrate: Option<Decimal>,
+    period: Option<String>,
+    error: Option<String>,
+    #[serde(skip)]
+    exit: i32,
+}
+
+const COLUMNS: [&str; 11] = [
+    "addr", "city", "zip", "code", "code_name", "loccode", "name", "rate", "localrate", "period",
+    "error",
+];
+
+impl Row {
+    fn new(query: &AddressQuery, result: &Result<TaxInfo, TaxInfoError>) -> Self {
+        let mut row = Row {
+            addr: query.addr.clone(),
+            city: query.city.clone(),
+            zip: query.zip.clone(),
+            code: None,
+            code_name: None,
+            loccode: None,
+            name: None,
+            rate: None,
+            localrate: None,
+            period: None,
+            error: None,
+            exit: exit_code(result),
+        };
+        let info = match result {
+            Ok(info) => Some(info),
+            Err(TaxInfoError::Dor((_, info))) | Err(TaxInfoError::InvalidLongLat(info)) => Some(info),
+            Err(_) => None,
+        };
+        if let Some(info) = info {
+            row.code = Some(info.code);
+            row.code_name = Some(info.code.name());
+            if !info.code.is_error() {
+                row.loccode = Some(info.loccode);
+                row.name = info.taxrate.as_ref().map(|t| t.name.clone());
+                row.rate = Some(info.rate);
+                row.localrate = Some(info.localrate);
+                row.period = info.effective_period().map(|p| p.to_string());
+            }
+        }
+        if let Err(e) = result {
+            row.error = Some(e.to_string());
+        }
+        row
+    }
+
+    /// For the table and csv, in the order of `COLUMNS`. Missing fields are empty.
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.addr.clone(),
+            self.city.clone(),
+            self.zip.clone(),
+            self.code.map(|c| c.number().to_string()).unwrap_or_default(),
+            self.code_name.unwrap_or_default().to_string(),
+            self.loccode.map(|l| l.to_string()).unwrap_or_default(),
+            self.name.clone().unwrap_or_default(),
+            self.rate.as_ref().map(|r| r.to_string()).unwrap_or_default(),
+            self.localrate.as_ref().map(|r| r.to_string()).unwrap_or_default(),
+            self.period.clone().unwrap_or_default(),
+            self.error.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    process::exit(match run(&args) {
+        Ok(exit) => exit,
+        Err((exit, message)) => {
+            eprintln!("{}", message);
+            exit
+        }
+    });
+}
+
+fn run(args: &[String]) -> Result<i32, (i32, String)> {
+    let usage = |message: &str| (EXIT_USAGE, format!("{}\n\n{}", message, USAGE));
+
+    let command = match args.first() {
+        Some(command) => command.as_str(),
+        None => return Err(usage("no command")),
+    };
+    if command == "help" || command == "--help" || command == "-h" {
+        println!("{}", USAGE);
+        return Ok(0);
+    }
+    let (positional, flags) = parse_flags(&args[1..]).map_err(|e| usage(&e))?;
+    let flag = |name: &str| flags.get(name).map(|value| value.as_str());
+    let required = |name: &str| flag(name).ok_or_else(|| usage(&format!("--{} is required", name)));
+
+    let format = match flag("format").unwrap_or("table") {
+        "table" => Format::Table,
+        "json" => Format::Json,
+        "csv" => Format::Csv,
+        other => return Err(usage(&format!("unknown format {}", other))),
+    };
+    let date: Option<Date> = match flag("date") {
+        Some(date) => Some(date.parse().map_err(|e: &str| usage(e))?),
+        None => None,
+    };
+
+    let mut builder = Client::builder();
+    if let Some(base_url) = flag("base-url") {
+        builder = builder.base_url(base_url);
+    }
+    let client = builder
+        .build()
+        .and_then(Client::from_async)
+        .map_err(|e| (EXIT_USAGE, e.to_string()))?;
+
+    let rows = match command {
+        "lookup" => {
+            let query = AddressQuery::new(required("addr")?, required("city")?, required("zip")?);
+            let result = match date {
+                Some(date) => client.get_as_of(&query.addr, &query.city, &query.zip, date),
+                None => client.get(&query.addr, &query.city, &query.zip),
+            };
+            vec![Row::new(&query, &result)]
+        }
+        "coords" => {
+            let lat: f64 = required("lat")?.parse().map_err(|_| usage("--lat is not a number"))?;
+            let lng: f64 = required("lng")?.parse().map_err(|_| usage("--lng is not a number"))?;
+            let result = match date {
+                Some(date) => client.get_by_coords_as_of(lat, lng, date),
+                None => client.get_by_coords(lat, lng),
+            };
+            vec![Row::new(&AddressQuery::new("", "", ""), &result)]
+        }
+        "batch" => {
+            if date.is_some() {
+                return Err(usage("--date isn't supported for batch"));
+            }
+            let path = positional.first().ok_or_else(|| usage("batch needs a csv file"))?;
+            let queries = read_queries(path)?;
+            let concurrency: usize = match flag("concurrency") {
+                Some(n) => n.parse().map_err(|_| usage("--concurrency is not a number"))?,
+                None => 4,
+            };
+            let results = client
+                .batch(queries.clone())
+                .concurrency(concurrency)
+                .on_progress(|p| eprint!("\r{}/{} done, {} failed", p.completed, p.total, p.failed))
+                .run();
+            eprintln!();
+            queries.iter().zip(&results).map(|(q, r)| Row::new(q, r)).collect()
+        }
+        other => return Err(usage(&format!("unknown command {}", other))),
+    };
+
+    print_rows(&rows, format, command == "batch").map_err(|e| (EXIT_IO, e.to_string()))?;
+    Ok(rows.iter().map(|row| row.exit).max().unwrap_or(0))
+}
+
+/// `--name value` and `--name=value` into a map, everything else in order
+fn parse_flags(args: &[String]) -> Result<(Vec<String>, HashMap<String, String>), String> {
+    let mut positional = Vec::new();
+    let mut flags = HashMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg.clone());
+            continue;
+        }
+        let arg = &arg[2..];
+        let (name, value) = match arg.find('=') {
+            Some(eq) => (arg[..eq].to_string(), arg[eq + 1..].to_string()),
+            None => {
+                let value = args.next().ok_or_else(|| format!("--{} needs a value", arg))?;
+                (arg.to_string(), value.clone())
+            }
+        };
+        flags.insert(name, value);
+    }
+    Ok((positional, flags))
+}
+
+/// Reads the addr, city and zip columns, whatever order they're in
+fn read_queries(path: &str) -> Result<Vec<AddressQuery>, (i32, String)> {
+    let io_error = |e: csv::Error| {
+        let exit = if e.is_io_error() { EXIT_IO } else { EXIT_DATA };
+        (exit, format!("{}: {}", path, e))
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .map_err(io_error)?;
+    let headers = reader.headers().map_err(io_error)?.clone();
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|h| names.iter().any(|n| h.eq_ignore_ascii_case(n)))
+            .ok_or_else(|| (EXIT_DATA, format!("{}: no {} column", path, names[0])))
+    };
+    let addr = column(&["addr", "address"])?;
+    let city = column(&["city"])?;
+    let zip = column(&["zip", "zipcode", "zip code"])?;
+
+    let mut queries = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(io_error)?;
+        let field = |col: usize| record.get(col).unwrap_or("");
+        queries.push(AddressQuery::new(field(addr), field(city), field(zip)));
+    }
+    Ok(queries)
+}
+
+fn print_rows(rows: &[Row], format: Format, many: bool) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    match format {
+        Format::Json => {
+            if many {
+                writeln!(out, "[")?;
+                for (i, row) in rows.iter().enumerate() {
+                    let comma = if i + 1 < rows.len() { "," } else { "" };
+                    writeln!(out, "  {}{}", serde_json::to_string(row)?, comma)?;
+                }
+                writeln!(out, "]")?;
+            } else {
+                for row in rows {
+                    writeln!(out, "{}", serde_json::to_string(row)?)?;
+                }
+            }
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record(&COLUMNS)?;
+            for row in rows {
+                writer.write_record(row.fields())?;
+            }
+            writer.flush()?;
+        }
+        Format::Table => {
+            let fields: Vec<Vec<String>> = rows.iter().map(Row::fields).collect();
+            let mut widths: Vec<usize> = COLUMNS.iter().map(|c| c.len()).collect();
+            for row in &fields {
+                for (width, field) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(field.chars().count());
+                }
+            }
+            let line = |out: &mut dyn Write, row: &[String]| {
+                let cells: Vec<String> = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(field, width)| format!("{:width$}", field, width = width))
+                    .collect();
+                writeln!(out, "{}", cells.join("  ").trim_end())
+            };
+            let header: Vec<String> = COLUMNS.iter().map(|c| c.to_string()).collect();
+            line(&mut out, &header)?;
+            for row in &fields {
+                line(&mut out, row)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn exit_code(result: &Result<TaxInfo, TaxInfoError>) -> i32 {
+    match result {
+        Ok(info) => info.code.number() as i32,
+        Err(TaxInfoError::Dor((code, _))) => code.number() as i32,
+        Err(TaxInfoError::InvalidLongLat(_)) => Code::InvalidLongLat.number() as i32,
+        Err(TaxInfoError::OutsideWashington(_)) => EXIT_DATA,
+        Err(TaxInfoError::NotXml(_)) | Err(TaxInfoError::Schema { .. }) => EXIT_PROTOCOL,
+        Err(TaxInfoError::Internal(_)) => EXIT_PROTOCOL,
+        Err(_) => EXIT_UNAVAILABLE,
+    }
+}
@@ -0,0 +1,189 @@
+//! The same lookups without async, for plain synchronous programs. Needs the `blocking` feature.
+//!
+//! ```no_run
+//! # #[cfg(feature = "reqwest")]
+//! # fn main() {
+//! let client = wataxrate::blocking::Client::new();
+//! match client.get("400 Broad St", "Seattle", "98109") {
+//!     Ok(taxinfo) => println!("Tax rate is {}", taxinfo.rate),
+//!     Err(e) => eprintln!("Error getting tax info: {}", e),
+//! }
+//! # }
+//! # #[cfg(not(feature = "reqwest"))]
+//! # fn main() {}
+//! ```
+//!
+//! Each [`Client`] runs the async client on a small tokio runtime of its own, so parsing,
+//! retries, caching and errors are exactly the same. Don't use it from inside an async runtime,
+//! tokio panics when runtimes are nested.
+
+use crate::batch::{self, AddressQuery, Progress};
+use crate::{ClientBuilder, Date, TaxInfo, TaxInfoError};
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::runtime::Runtime;
+
+/// A synchronous [`crate::Client`].
+///
+/// Calls from several threads take turns, use the async client for lookups in parallel, or
+/// [`Client::batch`].
+#[derive(Debug)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Mutex<Runtime>,
+}
+
+impl Client {
+    /// A client with the defaults, pointed at DOR.
+    ///
+    /// # Panics
+    /// When the async client can't be built, see [`crate::Client::new`], or the runtime can't be
+    /// started. Use [`Client::from_async`] if you'd rather get an error.
+    #[cfg(feature = "reqwest")]
+    pub fn new() -> Self {
+        Self::from_async(crate::Client::new())
+            .expect("blocking::Client::new() failed to start a runtime")
+    }
+
+    /// Set up like any [`crate::Client`], then finish with [`Client::from_async`]
+    pub fn builder() -> ClientBuilder {
+        crate::Client::builder()
+    }
+
+    /// Wraps an async client, sharing its connection pool, cache and rate limiter with any
+    /// clones of it
+    pub fn from_async(inner: crate::Client) -> Result<Self, TaxInfoError> {
+        let runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()
+            .map_err(|_| TaxInfoError::Internal("could not start a tokio runtime"))?;
+        Ok(Client { inner, runtime: Mutex::new(runtime) })
+    }
+
+    /// The async client underneath
+    pub fn inner(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    /// See [`crate::Client::get`]
+    pub fn get(&self, addr: &str, city: &str, zip: &str) -> Result<TaxInfo, TaxInfoError> {
+        self.block_on(self.inner.get(addr, city, zip))
+    }
+
+    /// See [`crate::Client::get_as_of`]
+    pub fn get_as_of(&self, addr: &str, city: &str, zip: &str, date: Date) -> Result<TaxInfo, TaxInfoError> {
+        self.block_on(self.inner.get_as_of(addr, city, zip, date))
+    }
+
+    /// See [`crate::Client::get_basic`]
+    pub fn get_basic(&self, addr: &str, city: &str, zip: &str) -> Result<TaxInfo, TaxInfoError> {
+        self.block_on(self.inner.get_basic(addr, city, zip))
+    }
+
+    /// See [`crate::Client::get_by_coords`]
+    pub fn get_by_coords(&self, lat: f64, lng: f64) -> Result<TaxInfo, TaxInfoError> {
+        self.block_on(self.inner.get_by_coords(lat, lng))
+    }
+
+    /// See [`crate::Client::get_by_coords_as_of`]
+    pub fn get_by_coords_as_of(&self, lat: f64, lng: f64, date: Date) -> Result<TaxInfo, TaxInfoError> {
+        self.block_on(self.inner.get_by_coords_as_of(lat, lng, date))
+    }
+
+    /// See [`crate::Client::get_by_coords_basic`]
+    pub fn get_by_coords_basic(&self, lat: f64, lng: f64) -> Result<TaxInfo, TaxInfoError> {
+        self.block_on(self.inner.get_by_coords_basic(lat, lng))
+    }
+
+    /// See [`crate::Client::batch`]. The lookups still run a few at a time, `run` blocks until
+    /// they're all done.
+    pub fn batch<I>(&self, queries: I) -> Batch<'_>
+    where
+        I: IntoIterator,
+        I::Item: Into<AddressQuery>,
+    {
+        Batch { client: self, inner: self.inner.batch(queries) }
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.lock().unwrap().block_on(future)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A batch of lookups, made with [`Client::batch`]
+pub struct Batch<'a> {
+    client: &'a Client,
+    inner: batch::Batch<'a>,
+}
+
+impl<'a> Batch<'a> {
+    /// Passed on to the async [`Batch::concurrency`](crate::batch::Batch::concurrency), the
+    /// lookups share the blocking client's runtime
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.inner = self.inner.concurrency(concurrency);
+        self
+    }
+
+    /// Called every time a lookup finishes
+    pub fn on_progress(mut self, on_progress: impl FnMut(Progress) + Send + 'a) -> Self {
+        self.inner = self.inner.on_progress(on_progress);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Runs every lookup, answering in the same order as the queries
+    pub fn run(self) -> Vec<Result<TaxInfo, TaxInfoError>> {
+        let Batch { client, inner } = self;
+        client.block_on(inner.run())
+    }
+}
+
+/// Has retries, reasonable timeouts, defaults, fully ready to go.
+///
+/// Starts a fresh client and runtime for every call, hold onto a [`Client`] yourself for more
+/// than one lookup.
+#[cfg(feature = "reqwest")]
+pub fn get(addr: &str, city: &str, zip: &str) -> Result<TaxInfo, TaxInfoError> {
+    Client::from_async(Client::builder().build()?)?.get(addr, city, zip)
+}
+
+/// No retries, just one attempt, no timeout, nothing
+#[cfg(feature = "reqwest")]
+pub fn get_basic(addr: &str, city: &str, zip: &str) -> Result<TaxInfo, TaxInfoError> {
+    Client::from_async(Client::builder().build()?)?.get_basic(addr, city, zip)
+}
+
+/// Has retries, reasonable timeouts, defaults, fully ready to go. Looks up by point instead of
+/// address.
+#[cfg(feature = "reqwest")]
+pub fn get_by_coords(lat: f64, lng: f64) -> Result<TaxInfo, TaxInfoError> {
+    Client::from_async(Client::builder().build()?)?.get_by_coords(lat, lng)
+}
+
+/// No retries, just one attempt, no timeout, nothing. Looks up by point instead of address.
+#[cfg(feature = "reqwest")]
+pub fn get_by_coords_basic(lat: f64, lng: f64) -> Result<TaxInfo, TaxInfoError> {
+    Client::from_async(Client::builder().build()?)?.get_by_coords_basic(lat, lng)
+}
+
+/// Has retries, reasonable timeouts, defaults, fully ready to go. Gets the rates that applied on
+/// `date` rather than today's.
+#[cfg(feature = "reqwest")]
+pub fn get_as_of(addr: &str, city: &str, zip: &str, date: Date) -> Result<TaxInfo, TaxInfoError> {
+    Client::from_async(Client::builder().build()?)?.get_as_of(addr, city, zip, date)
+}
@@ -0,0 +1,250 @@
+//! A cache that lives in a file, so it survives restarts.
+
+use super::{expires_at, Cache, CacheKey, CacheStats};
+use crate::{Address, RatePeriod, TaxInfo, TaxRate};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const HEADER: [&str; 14] = [
+    "addr", "city", "zip", "as_of", "fetched_at", "expires", "period", "loccode", "rate", "code",
+    "localrate", "debughint", "address", "taxrate",
+];
+
+/// Keeps lookups in a csv file, one row per lookup, along with when it was fetched and the rate
+/// period it's for.
+///
+/// Rows are appended as lookups come in, and the file is rewritten without stale rows when it's
+/// opened. Rows from an earlier rate period are dropped, so everything gets looked up again after
+/// the quarter rolls over, except lookups for a past period which never go stale. Rows that
+/// can't be read, like one that was half written when the process died, are skipped rather than
+/// failing the whole file.
+#[derive(Debug)]
+pub struct FileCache {
+    path: PathBuf,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    entries: HashMap<CacheKey, Stored>,
+    writer: Option<csv::Writer<File>>,
+    stats: CacheStats,
+    skipped: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Stored {
+    info: TaxInfo,
+    fetched_at: SystemTime,
+    /// None for never
+    expires: Option<SystemTime>,
+}
+
+impl Stored {
+    fn is_stale(&self, now: SystemTime) -> bool {
+        self.expires.map(|expires| expires <= now).unwrap_or(false)
+    }
+}
+
+impl FileCache {
+    /// Loads the file, creating it if it doesn't exist. Only fails when the file can't be read
+    /// or written at all, bad rows inside it are skipped.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let now = SystemTime::now();
+
+        let mut entries = HashMap::new();
+        let mut skipped = 0;
+        let mut expirations = 0;
+        if path.exists() {
+            let mut reader = csv::ReaderBuilder::new()
+                .flexible(true)
+                .has_headers(true)
+                .from_path(&path)?;
+            for record in reader.records() {
+                match record.ok().as_ref().and_then(parse_record) {
+                    Some((_, stored)) if stored.is_stale(now) => expirations += 1,
+                    // Later rows are newer, so they win
+                    Some((key, stored)) => {
+                        entries.insert(key, stored);
+                    }
+                    None => skipped += 1,
+                }
+            }
+        }
+        if skipped > 0 {
+            warn!("skipped {} unreadable rows in cache file {}", skipped, path.display());
+        }
+
+        let cache = FileCache {
+            path,
+            inner: Mutex::new(Inner {
+                entries,
+                writer: None,
+                stats: CacheStats { expirations, ..CacheStats::default() },
+                skipped,
+            }),
+        };
+        cache.compact()?;
+        Ok(cache)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How many rows couldn't be read when the file was opened
+    pub fn skipped(&self) -> usize {
+        self.inner.lock().unwrap().skipped
+    }
+
+    /// When a cached lookup was fetched from DOR
+    pub fn fetched_at(&self, key: &CacheKey) -> Option<SystemTime> {
+        self.inner.lock().unwrap().entries.get(key).map(|stored| stored.fetched_at)
+    }
+
+    /// Rewrites the file with only the rows that are still good. The new file is written next
+    /// to the old one and moved over it, so a crash part way through leaves the old file.
+    pub fn compact(&self) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = SystemTime::now();
+        let before = inner.entries.len();
+        inner.entries.retain(|_, stored| !stored.is_stale(now));
+        inner.stats.expirations += (before - inner.entries.len()) as u64;
+
+        // Close the append handle before replacing the file out from under it
+        inner.writer = None;
+
+        let tmp = self.path.with_extension("tmp");
+        let file = File::create(&tmp)?;
+        {
+            let mut writer = csv::Writer::from_writer(&file);
+            writer.write_record(&HEADER)?;
+            for (key, stored) in &inner.entries {
+                writer.write_record(&to_record(key, stored))?;
+            }
+            writer.flush()?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+
+        let file = OpenOptions::new().append(true).open(&self.path)?;
+        inner.writer = Some(csv::WriterBuilder::new().has_headers(false).from_writer(file));
+        Ok(())
+    }
+}
+
+impl Cache for FileCache {
+    fn get(&self, key: &CacheKey) -> Option<TaxInfo> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.entries.get(key).map(|stored| stored.is_stale(SystemTime::now())) {
+            None => {
+                inner.stats.misses += 1;
+                None
+            }
+            Some(true) => {
+                inner.entries.remove(key);
+                inner.stats.expirations += 1;
+                inner.stats.misses += 1;
+                None
+            }
+            Some(false) => {
+                inner.stats.hits += 1;
+                inner.entries.get(key).map(|stored| stored.info.clone())
+            }
+        }
+    }
+
+    fn insert(&self, key: CacheKey, info: &TaxInfo) {
+        let stored = Stored {
+            info: info.clone(),
+            fetched_at: SystemTime::now(),
+            expires: expires_at(&key, info),
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        let written = match inner.writer.as_mut() {
+            Some(writer) => writer
+                .write_record(&to_record(&key, &stored))
+                .and_then(|_| writer.flush().map_err(csv::Error::from)),
+            None => Ok(()),
+        };
+        if let Err(e) = written {
+            // Still useful in memory, it just won't survive a restart
+            warn!("failed writing to cache file {}: {:?}", self.path.display(), e);
+        }
+        inner.entries.insert(key, stored);
+    }
+
+    fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap();
+        CacheStats { len: inner.entries.len(), ..inner.stats }
+    }
+}
+
+fn to_record(key: &CacheKey, stored: &Stored) -> Vec<String> {
+    let info = &stored.info;
+    let secs = |time: SystemTime| {
+        time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0).to_string()
+    };
+    vec![
+        key.addr().to_string(),
+        key.city().to_string(),
+        key.zip().to_string(),
+        key.period().map(|p| p.to_string()).unwrap_or_default(),
+        secs(stored.fetched_at),
+        stored.expires.map(secs).unwrap_or_default(),
+        info.effective_period().map(|p| p.to_string()).unwrap_or_default(),
+        info.loccode.to_string(),
+        info.rate.to_string(),
+        info.code.number().to_string(),
+        info.localrate.to_string(),
+        info.debughint.clone().unwrap_or_default(),
+        info.address.as_ref().map(Address::to_xml).unwrap_or_default(),
+        info.taxrate.as_ref().map(TaxRate::to_xml).unwrap_or_default(),
+    ]
+}
+
+/// None for anything that doesn't parse, which is how bad rows get skipped
+fn parse_record(record: &csv::StringRecord) -> Option<(CacheKey, Stored)> {
+    if record.len() != HEADER.len() {
+        return None;
+    }
+    let optional = |i: usize| Some(&record[i]).filter(|v| !v.is_empty());
+    let time = |secs: &str| secs.parse().ok().map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+
+    let mut key = CacheKey::new(&record[0], &record[1], &record[2]);
+    if let Some(as_of) = optional(3) {
+        key = key.for_period(as_of.parse::<RatePeriod>().ok()?);
+    }
+    let fetched_at = time(&record[4])?;
+    let expires = match optional(5) {
+        Some(expires) => Some(time(expires)?),
+        None => None,
+    };
+    if let Some(period) = optional(6) {
+        period.parse::<RatePeriod>().ok()?;
+    }
+    let address = match optional(12) {
+        Some(xml) => Some(Address::from_xml(xml).ok()?),
+        None => None,
+    };
+    let taxrate = match optional(13) {
+        Some(xml) => Some(TaxRate::from_xml(xml).ok()?),
+        None => None,
+    };
+    let info = TaxInfo {
+        loccode: record[7].parse().ok()?,
+        rate: record[8].parse().ok()?,
+        code: record[9].parse().ok()?,
+        localrate: record[10].parse().ok()?,
+        debughint: optional(11).map(|v| v.to_string()),
+        address,
+        taxrate,
+    };
+    Some((key, Stored { info, fetched_at, expires }))
+}
@@ -0,0 +1,137 @@
+//! An in-process LRU cache.
+
+use super::{expires_at, Cache, CacheKey, CacheStats};
+use crate::TaxInfo;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+const DEFAULT_CAPACITY: usize = 10_000;
+
+/// Keeps up to `capacity` lookups in memory, dropping the least recently used when full.
+///
+/// Current rates never outlive the rate period they're for, and entries can expire sooner with
+/// [`MemoryCache::with_ttl`].
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    ttl: Option<Duration>,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: HashMap<CacheKey, Entry>,
+    /// Last use to key, oldest first
+    recency: BTreeMap<u64, CacheKey>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+#[derive(Debug)]
+struct Entry {
+    info: TaxInfo,
+    /// None for never
+    expires: Option<SystemTime>,
+    last_used: u64,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        MemoryCache {
+            capacity: capacity.max(1),
+            ttl: None,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Expire entries after `ttl`, if that comes before the end of their rate period
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.recency.clear();
+    }
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Inner {
+    fn touch(&mut self, key: &CacheKey) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.last_used);
+            entry.last_used = tick;
+            self.recency.insert(tick, key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.last_used);
+        }
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, key: &CacheKey) -> Option<TaxInfo> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.entries.get(key).map(|entry| entry.expires) {
+            None => {
+                inner.stats.misses += 1;
+                return None;
+            }
+            Some(Some(expires)) if expires <= SystemTime::now() => {
+                inner.remove(key);
+                inner.stats.expirations += 1;
+                inner.stats.misses += 1;
+                return None;
+            }
+            Some(_) => {}
+        }
+
+        inner.touch(key);
+        inner.stats.hits += 1;
+        inner.entries.get(key).map(|entry| entry.info.clone())
+    }
+
+    fn insert(&self, key: CacheKey, info: &TaxInfo) {
+        let ttl = self.ttl.map(|ttl| SystemTime::now() + ttl);
+        let expires = match (expires_at(&key, info), ttl) {
+            (Some(period_end), Some(ttl)) => Some(period_end.min(ttl)),
+            (period_end, ttl) => period_end.or(ttl),
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.remove(&key);
+        while inner.entries.len() >= self.capacity {
+            let oldest = match inner.recency.values().next() {
+                Some(oldest) => oldest.clone(),
+                None => break,
+            };
+            inner.remove(&oldest);
+            inner.stats.evictions += 1;
+        }
+
+        inner.entries.insert(key.clone(), Entry { info: info.clone(), expires, last_used: 0 });
+        inner.touch(&key);
+    }
+
+    fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap();
+        CacheStats { len: inner.entries.len(), ..inner.stats }
+    }
+}
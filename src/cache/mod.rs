@@ -0,0 +1,119 @@
+//! Caching lookups, so the same address doesn't go to DOR over and over.
+//!
+//! Give a [`Cache`] to [`crate::ClientBuilder::cache`] and [`crate::Client::get`] checks it before
+//! making a request. Only successful address lookups are cached. [`MemoryCache`] lasts as long as
+//! the process, [`FileCache`] survives restarts.
+
+mod file;
+mod memory;
+
+pub use file::FileCache;
+pub use memory::MemoryCache;
+
+use crate::normalize::normalize;
+use crate::{RatePeriod, TaxInfo};
+use std::fmt::Debug;
+use std::time::SystemTime;
+
+/// Somewhere to keep `TaxInfo`s between lookups. Implementations are shared between tasks, so
+/// they take `&self` and handle their own locking.
+pub trait Cache: Debug + Send + Sync {
+    /// A cached answer, if there is one that hasn't expired
+    fn get(&self, key: &CacheKey) -> Option<TaxInfo>;
+
+    fn insert(&self, key: CacheKey, info: &TaxInfo);
+
+    fn stats(&self) -> CacheStats;
+}
+
+/// A normalized `(addr, city, zip)`, so `400 Broad Street` and `400  broad st.` share an entry.
+///
+/// Lookups for a past date are keyed by the period too, see [`CacheKey::for_period`].
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CacheKey {
+    addr: String,
+    city: String,
+    zip: String,
+    period: Option<RatePeriod>,
+}
+
+impl CacheKey {
+    pub fn new(addr: &str, city: &str, zip: &str) -> Self {
+        CacheKey {
+            addr: normalize(addr),
+            city: normalize(city),
+            zip: zip.chars().filter(|c| c.is_ascii_alphanumeric()).collect(),
+            period: None,
+        }
+    }
+
+    /// A key for the rates in a specific period, rather than whatever's current. Rates for a
+    /// period don't change once it's over, so entries for a past period never expire. Entries
+    /// for the current period expire when it ends, like current rates do.
+    pub fn for_period(mut self, period: RatePeriod) -> Self {
+        self.period = Some(period);
+        self
+    }
+
+    /// The period asked for, None for the current rates
+    pub fn period(&self) -> Option<RatePeriod> {
+        self.period
+    }
+
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    pub fn city(&self) -> &str {
+        &self.city
+    }
+
+    pub fn zip(&self) -> &str {
+        &self.zip
+    }
+}
+
+/// How a cache has been doing
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to make room
+    pub evictions: u64,
+    /// Entries dropped because their rate period ended
+    pub expirations: u64,
+    /// Entries in the cache right now
+    pub len: usize,
+}
+
+impl CacheStats {
+    /// Fraction of `get`s that were hits, 0 when there haven't been any
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// The rate period a `TaxInfo` is for. That's the period DOR sent, or the current one when it
+/// didn't send one we understand.
+fn period_of(info: &TaxInfo) -> RatePeriod {
+    info.effective_period().unwrap_or_else(RatePeriod::current)
+}
+
+/// When a cached `TaxInfo` stops being good, None for never. Current rates are good until the
+/// end of their period, rates asked for by period are good forever once that period is over.
+fn expires_at(key: &CacheKey, info: &TaxInfo) -> Option<SystemTime> {
+    let ends_at = match key.period {
+        Some(period) => period.ends_at(),
+        None => period_of(info).ends_at(),
+    };
+    if key.period.is_some() && ends_at <= SystemTime::now() {
+        None
+    } else {
+        Some(ends_at)
+    }
+}
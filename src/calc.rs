@@ -0,0 +1,119 @@
+//! Working out how much tax is owed, from a `TaxInfo` and an amount.
+//!
+//! WA rounds tax to the nearest cent, with half a cent or more rounding up. Rounding happens on
+//! the combined tax, and the state's share is rounded the same way, so the local share is
+//! whatever's left. That way state + local always adds up to the total.
+//!
+//! ```
+//! use wataxrate::calc::{calculate_invoice, Rounding};
+//! # fn example(info: wataxrate::TaxInfo) {
+//! let price: wataxrate::Decimal = "19.99".parse().unwrap();
+//! let tax = calculate_invoice(vec![(&info, price), (&info, price)], Rounding::PerInvoice);
+//! println!("total tax {}", tax.total.total);
+//! # }
+//! ```
+
+use crate::{Decimal, TaxInfo};
+
+/// Where rounding to cents happens when there's more than one line
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Rounding {
+    /// Round each line, and add up the rounded lines
+    PerLine,
+    /// Add up the exact tax on each line, and round once at the end
+    PerInvoice,
+}
+
+/// Tax on an amount, split into state and local
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct TaxBreakdown {
+    /// The amount the tax is on
+    pub taxable: Decimal,
+    pub state: Decimal,
+    pub local: Decimal,
+    /// `state + local`
+    pub total: Decimal,
+}
+
+impl TaxBreakdown {
+    /// Exact tax, not rounded
+    fn exact(info: &TaxInfo, amount: Decimal) -> Self {
+        let (staterate, localrate) = rates(info);
+        let state = amount * staterate;
+        let local = amount * localrate;
+        TaxBreakdown { taxable: amount, state, local, total: state + local }
+    }
+
+    /// Rounded to cents the way WA does it
+    fn rounded(&self) -> Self {
+        let total = self.total.round_dp(2);
+        let state = self.state.round_dp(2);
+        TaxBreakdown { taxable: self.taxable, state, local: total - state, total }
+    }
+}
+
+impl std::ops::Add for TaxBreakdown {
+    type Output = TaxBreakdown;
+
+    fn add(self, other: TaxBreakdown) -> TaxBreakdown {
+        TaxBreakdown {
+            taxable: self.taxable + other.taxable,
+            state: self.state + other.state,
+            local: self.local + other.local,
+            total: self.total + other.total,
+        }
+    }
+}
+
+/// Tax on each line of an invoice, and the invoice as a whole
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct InvoiceTax {
+    /// One per line, in order. Rounded with `Rounding::PerLine`, exact with
+    /// `Rounding::PerInvoice`.
+    pub lines: Vec<TaxBreakdown>,
+    /// Always rounded to cents
+    pub total: TaxBreakdown,
+}
+
+/// Tax on one amount, rounded to cents.
+///
+/// The state and local rates come from `TaxInfo::taxrate` when DOR sent it, otherwise the local
+/// rate is `TaxInfo::localrate` and the state rate is the rest of `TaxInfo::rate`. A `TaxInfo`
+/// with an error `Code` has garbage rates, and so gives garbage tax.
+pub fn calculate(info: &TaxInfo, amount: Decimal) -> TaxBreakdown {
+    TaxBreakdown::exact(info, amount).rounded()
+}
+
+/// Tax on an invoice, where each line can be going somewhere different
+pub fn calculate_invoice<'a, I>(lines: I, rounding: Rounding) -> InvoiceTax
+where
+    I: IntoIterator<Item = (&'a TaxInfo, Decimal)>,
+{
+    let exact: Vec<TaxBreakdown> = lines
+        .into_iter()
+        .map(|(info, amount)| TaxBreakdown::exact(info, amount))
+        .collect();
+
+    match rounding {
+        Rounding::PerLine => {
+            let lines: Vec<TaxBreakdown> = exact.iter().map(TaxBreakdown::rounded).collect();
+            let total = lines.iter().fold(TaxBreakdown::default(), |sum, line| sum + *line);
+            InvoiceTax { lines, total }
+        }
+        Rounding::PerInvoice => {
+            let total = exact
+                .iter()
+                .fold(TaxBreakdown::default(), |sum, line| sum + *line)
+                .rounded();
+            InvoiceTax { lines: exact, total }
+        }
+    }
+}
+
+/// (state rate, local rate)
+fn rates(info: &TaxInfo) -> (Decimal, Decimal) {
+    match &info.taxrate {
+        Some(taxrate) => (taxrate.staterate, taxrate.localrate),
+        None => (info.rate - info.localrate, info.localrate),
+    }
+}
@@ -0,0 +1,391 @@
+//! A reusable [`Client`] that keeps one pooled HTTP client around, rather than building a new
+//! connection for every lookup.
+
+use crate::batch::{AddressQuery, Batch, CsvBatch};
+use crate::cache::{Cache, CacheKey, CacheStats};
+use crate::ratelimit::RateLimiter;
+use crate::retry::{Attempt, RetryPolicy, RetryReport, StopReason};
+#[cfg(feature = "reqwest")]
+use crate::transport::ReqwestTransport;
+use crate::transport::Transport;
+use crate::{parse_response, Coordinates, Date, RatePeriod, TaxInfo, TaxInfoError};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use url::Url;
+
+const DOR_BASE_URL: &str = "https://webgis.dor.wa.gov/webapi/AddressRates.aspx";
+
+
+/// Talks to DOR (or anything that speaks the same XML URL interface).
+///
+/// Cloning is cheap, the underlying connection pool is shared.
+///
+/// Lookups have to run on a tokio 0.2 runtime with its timer enabled, whatever the transport:
+/// waiting between retries, attempt timeouts and the rate limiter all use tokio's timer, and
+/// panic outside of one. `blocking::Client`, behind the `blocking` feature, runs one for you.
+#[derive(Clone, Debug)]
+pub struct Client {
+    transport: Arc<dyn Transport>,
+    base_url: Url,
+    retry: RetryPolicy,
+    cache: Option<Arc<dyn Cache>>,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl Client {
+    /// A client with the defaults, pointed at DOR.
+    ///
+    /// # Panics
+    /// Like `reqwest::Client::new`, this panics if the TLS backend can't be initialized. Use
+    /// [`Client::builder`] if you'd rather get an error.
+    #[cfg(feature = "reqwest")]
+    pub fn new() -> Self {
+        Self::builder()
+            .build()
+            .expect("Client::new() failed to build the default client")
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// Has retries, reasonable timeouts, defaults, fully ready to go.
+    ///
+    /// Answers from the cache when there is one and it has the address.
+    pub async fn get(&self, addr: &str, city: &str, zip: &str) -> Result<TaxInfo, TaxInfoError> {
+        let key = CacheKey::new(addr, city, zip);
+        self.cached(key, &[("addr", addr), ("city", city), ("zip", zip)]).await
+    }
+
+    /// Like [`Client::get`], but for the rates that applied on `date` instead of today's. Handy
+    /// for refunds and amended returns.
+    ///
+    /// DOR answers with the rates for the whole quarter `date` is in, see
+    /// [`TaxInfo::effective_period`]. Answers for some other quarter aren't cached.
+    pub async fn get_as_of(
+        &self,
+        addr: &str,
+        city: &str,
+        zip: &str,
+        date: Date,
+    ) -> Result<TaxInfo, TaxInfoError> {
+        let key = CacheKey::new(addr, city, zip).for_period(RatePeriod::of(date));
+        let date = date.to_dor_string();
+        self.cached(key, &[("addr", addr), ("city", city), ("zip", zip), ("date", date.as_str())]).await
+    }
+
+    /// No retries, just one attempt, no timeout, nothing. Still waits its turn with the rate
+    /// limiter, if there is one.
+    pub async fn get_basic(&self, addr: &str, city: &str, zip: &str) -> Result<TaxInfo, TaxInfoError> {
+        self.throttle().await;
+        self.fetch(&[("addr", addr), ("city", city), ("zip", zip)]).await
+    }
+
+    /// Looks up the tax info for a point, with retries and timeouts like [`Client::get`].
+    ///
+    /// Points outside WA are rejected before any request is made.
+    pub async fn get_by_coords(&self, lat: f64, lng: f64) -> Result<TaxInfo, TaxInfoError> {
+        let (lat, lng) = coords_query(lat, lng)?;
+        let query = [("lat", lat.as_str()), ("lng", lng.as_str())];
+        self.with_retries(|| self.fetch(&query)).await
+    }
+
+    /// Like [`Client::get_by_coords`], but for the rates that applied on `date`
+    pub async fn get_by_coords_as_of(&self, lat: f64, lng: f64, date: Date) -> Result<TaxInfo, TaxInfoError> {
+        let (lat, lng) = coords_query(lat, lng)?;
+        let date = date.to_dor_string();
+        let query = [("lat", lat.as_str()), ("lng", lng.as_str()), ("date", date.as_str())];
+        self.with_retries(|| self.fetch(&query)).await
+    }
+
+    /// One attempt at looking up the tax info for a point, no timeout.
+    pub async fn get_by_coords_basic(&self, lat: f64, lng: f64) -> Result<TaxInfo, TaxInfoError> {
+        let (lat, lng) = coords_query(lat, lng)?;
+        self.throttle().await;
+        self.fetch(&[("lat", lat.as_str()), ("lng", lng.as_str())]).await
+    }
+
+    /// Sets up lookups for a bunch of addresses, which run a few at a time. Nothing happens until
+    /// the batch is run.
+    ///
+    /// ```no_run
+    /// # async fn example(client: wataxrate::Client) {
+    /// let results = client
+    ///     .batch(vec![("400 Broad St", "Seattle", "98109")])
+    ///     .concurrency(8)
+    ///     .on_progress(|p| println!("{}/{}", p.completed, p.total))
+    ///     .run()
+    ///     .await;
+    /// # }
+    /// ```
+    pub fn batch<I>(&self, queries: I) -> Batch<'_>
+    where
+        I: IntoIterator,
+        I::Item: Into<AddressQuery>,
+    {
+        Batch::new(self, queries.into_iter().map(Into::into).collect())
+    }
+
+    /// Sets up lookups for every row of a csv file, with the results written to another, see
+    /// [`CsvBatch`]. Nothing happens until the batch is run.
+    pub fn csv_batch(&self, input: impl Into<PathBuf>, output: impl Into<PathBuf>) -> CsvBatch<'_> {
+        CsvBatch::new(self, input.into(), output.into())
+    }
+
+    /// How the cache has been doing, None when there isn't one
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Looks in the cache, and on a miss goes to DOR and fills the cache in
+    async fn cached(&self, key: CacheKey, query: &[(&str, &str)]) -> Result<TaxInfo, TaxInfoError> {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return self.with_retries(|| self.fetch(query)).await,
+        };
+        if let Some(info) = cache.get(&key) {
+            debug!("cache hit for {:?}", key);
+            return Ok(info);
+        }
+
+        let info = self.with_retries(|| self.fetch(query)).await?;
+        match key.period() {
+            // DOR can ignore or clamp the date, so only keep rates for the period asked for
+            Some(period) if info.effective_period() != Some(period) => {
+                debug!("asked for {} but DOR sent {:?}, not caching", period, info.effective_period());
+            }
+            _ => cache.insert(key, &info),
+        }
+        Ok(info)
+    }
+
+    /// The rate limiter, None when there isn't one
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+
+    /// Waits for the rate limiter, if there is one
+    async fn throttle(&self) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            let waited = rate_limiter.acquire().await;
+            if waited > Duration::from_secs(0) {
+                debug!("waited {:?} for the rate limiter", waited);
+            }
+        }
+    }
+
+    /// How lookups like [`Client::get`] retry
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Runs attempts until one works, or the retry policy says to stop, throttling before each
+    /// one. Time spent waiting on the rate limiter doesn't count against the attempt's timeout,
+    /// but does count against the overall deadline.
+    async fn with_retries<F, Fut>(&self, mut attempt: F) -> Result<TaxInfo, TaxInfoError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<TaxInfo, TaxInfoError>>,
+    {
+        let started = Instant::now();
+        let mut attempts: Vec<Attempt> = Vec::new();
+        let give_up = |reason, attempts| {
+            let report = RetryReport { reason, attempts, elapsed: started.elapsed() };
+            TaxInfoError::NoMoreRetries(Box::new(report))
+        };
+
+        while attempts.len() < self.retry.attempts() {
+            if !attempts.is_empty() {
+                let delay = self.retry.delay(attempts.len());
+                if !self.retry.has_time_for(started, delay) {
+                    return Err(give_up(StopReason::Deadline, attempts));
+                }
+                debug!("waiting {:?} before retrying", delay);
+                tokio::time::delay_for(delay).await;
+            }
+
+            self.throttle().await;
+            let timeout = match self.retry.timeout_from(started) {
+                Some(timeout) => timeout,
+                None => return Err(give_up(StopReason::Deadline, attempts)),
+            };
+            let attempt_started = Instant::now();
+            let error = match tokio::time::timeout(timeout, attempt()).await {
+                Ok(Ok(r)) => return Ok(r),
+                Ok(Err(e)) => {
+                    if !e.retryable() {
+                        return Err(e);
+                    }
+                    e
+                }
+                Err(_) => TaxInfoError::Timeout(timeout),
+            };
+            debug!("attempt {} failed: {}", attempts.len() + 1, error);
+            attempts.push(Attempt {
+                error,
+                started: attempt_started - started,
+                took: attempt_started.elapsed(),
+            });
+        }
+        Err(give_up(StopReason::MaxAttempts, attempts))
+    }
+
+    async fn fetch(&self, query: &[(&str, &str)]) -> Result<TaxInfo, TaxInfoError> {
+        let mut request = self.base_url.clone();
+        request.query_pairs_mut()
+            .append_pair("output", "xml")
+            .extend_pairs(query);
+
+        debug!("URL to GET from dor {}", request);
+        let response = self.transport.get(request).await?;
+
+        debug!("raw string from DOR {}", response.body);
+
+        if !response.is_success() {
+            return Err(TaxInfoError::Status { status: response.status, body: response.body });
+        }
+
+        parse_response(&response.body)
+    }
+}
+
+/// The query for a point, or an error without making a request when it's nowhere near WA
+fn coords_query(lat: f64, lng: f64) -> Result<(String, String), TaxInfoError> {
+    let coords = Coordinates::new(lat, lng);
+    if !coords.in_washington() {
+        return Err(TaxInfoError::OutsideWashington(coords));
+    }
+    Ok((lat.to_string(), lng.to_string()))
+}
+
+#[cfg(feature = "reqwest")]
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Configures a [`Client`]. Everything has a default, so `Client::builder().build()` is fine.
+/// Without the `reqwest` feature there's no default transport, so one has to be given with
+/// [`ClientBuilder::transport`].
+#[derive(Debug)]
+pub struct ClientBuilder {
+    base_url: String,
+    retry: RetryPolicy,
+    #[cfg(feature = "reqwest")]
+    connect_timeout: Option<Duration>,
+    transport: Option<Arc<dyn Transport>>,
+    cache: Option<Arc<dyn Cache>>,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl ClientBuilder {
+    /// Where to send lookups, without a query string. Defaults to DOR's `AddressRates.aspx`.
+    ///
+    /// Handy for pointing at a local stand-in during tests, or at a proxy.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// How long a single attempt in [`Client::get`] may take. Defaults to 7 seconds.
+    ///
+    /// Shorthand for setting [`RetryPolicy::attempt_timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.retry = self.retry.attempt_timeout(timeout);
+        self
+    }
+
+    /// How long establishing a connection may take. No limit by default.
+    ///
+    /// Only applies to the default transport, one given to [`ClientBuilder::transport`] handles
+    /// its own connections. Needs the `reqwest` feature.
+    #[cfg(feature = "reqwest")]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Make requests with this rather than reqwest, see [`crate::transport`]
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// How many times [`Client::get`] tries before giving up. Defaults to 3.
+    ///
+    /// Shorthand for setting [`RetryPolicy::max_attempts`], and panics on 0 like it does.
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.retry = self.retry.max_attempts(max_attempts);
+        self
+    }
+
+    /// Backoff, jitter, timeouts and all, replacing anything set with
+    /// [`ClientBuilder::timeout`] or [`ClientBuilder::max_attempts`]. Defaults to
+    /// [`RetryPolicy::new`].
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Check this cache before going to DOR, e.g. a [`crate::cache::MemoryCache`]. No cache by
+    /// default.
+    pub fn cache(mut self, cache: impl Cache + 'static) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Like [`ClientBuilder::cache`], for a cache that's shared with something else
+    pub fn shared_cache(mut self, cache: Arc<dyn Cache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Every request waits its turn with this. Pass clones of the same limiter to several clients
+    /// to share a limit between them. No limit by default.
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn build(self) -> Result<Client, TaxInfoError> {
+        let base_url = Url::parse(&self.base_url)
+            .map_err(|_| TaxInfoError::Internal("base url is not a valid url"))?;
+
+        let transport = match self.transport {
+            Some(transport) => transport,
+            #[cfg(feature = "reqwest")]
+            None => Arc::new(ReqwestTransport::with_connect_timeout(self.connect_timeout)?),
+            #[cfg(not(feature = "reqwest"))]
+            None => {
+                return Err(TaxInfoError::Internal(
+                    "no transport, the reqwest feature is off so one has to be given",
+                ))
+            }
+        };
+
+        Ok(Client {
+            transport,
+            base_url,
+            retry: self.retry,
+            cache: self.cache,
+            rate_limiter: self.rate_limiter,
+        })
+    }
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        ClientBuilder {
+            base_url: DOR_BASE_URL.to_string(),
+            retry: RetryPolicy::new(),
+            #[cfg(feature = "reqwest")]
+            connect_timeout: None,
+            transport: None,
+            cache: None,
+            rate_limiter: None,
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Latitude/longitude, for looking up tax info by point rather than by street address.
+
+// A box around the whole state, padded a little so points right on the border still get sent
+// to DOR, which knows the real boundary.
+const MIN_LAT: f64 = 45.54;
+const MAX_LAT: f64 = 49.01;
+const MIN_LNG: f64 = -124.86;
+const MAX_LNG: f64 = -116.91;
+
+/// A point, in decimal degrees.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Coordinates {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl Coordinates {
+    pub fn new(lat: f64, lng: f64) -> Self {
+        Coordinates { lat, lng }
+    }
+
+    /// True when the point is inside a bounding box around WA State. Points that pass can still
+    /// be outside WA (the box is a box), DOR will answer those with `Code::InvalidLongLat`.
+    pub fn in_washington(&self) -> bool {
+        (MIN_LAT..=MAX_LAT).contains(&self.lat) && (MIN_LNG..=MAX_LNG).contains(&self.lng)
+    }
+}
@@ -0,0 +1,310 @@
+//! Exact decimal numbers, for rates and money.
+//!
+//! DOR sends rates like `0.101`, which `f32` can't hold exactly. Multiplying that by a price and
+//! rounding to cents can then land on the wrong side of a half cent.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// More digits than this after the point isn't something DOR or money needs
+const MAX_SCALE: u32 = 28;
+
+/// A decimal number, `mantissa / 10^scale`, that keeps the digits it was given. `0.100` parses
+/// and prints as `0.100`, but is equal to `0.1`.
+///
+/// Arithmetic is exact, and panics on overflow like integer arithmetic does. Overflow needs
+/// numbers far bigger than any rate or invoice. Comparing never panics.
+#[derive(Copy, Clone, Debug)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// `mantissa / 10^scale`, so `Decimal::new(101, 3)` is `0.101`
+    ///
+    /// # Panics
+    /// When scale is more than 28
+    pub fn new(mantissa: i128, scale: u32) -> Self {
+        assert!(scale <= MAX_SCALE, "decimal scale can be at most {}", MAX_SCALE);
+        Decimal { mantissa, scale }
+    }
+
+    /// An amount of money in cents, `from_cents(1999)` is `19.99`
+    pub fn from_cents(cents: i64) -> Self {
+        Decimal::new(cents as i128, 2)
+    }
+
+    pub fn zero() -> Self {
+        Decimal::new(0, 0)
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// Digits after the decimal point
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa < 0
+    }
+
+    /// Rounds to `dp` digits after the point, halves away from zero. Does nothing when there are
+    /// already `dp` digits or fewer.
+    pub fn round_dp(&self, dp: u32) -> Self {
+        if self.scale <= dp {
+            return *self;
+        }
+        let divisor = pow10(self.scale - dp);
+        let quotient = self.mantissa / divisor;
+        let remainder = (self.mantissa % divisor).abs();
+        let rounded = if remainder * 2 >= divisor {
+            quotient + self.mantissa.signum()
+        } else {
+            quotient
+        };
+        Decimal::new(rounded, dp)
+    }
+
+    /// In cents, rounding to the nearest cent first
+    pub fn to_cents(&self) -> i64 {
+        self.round_dp(2).rescale(2).mantissa as i64
+    }
+
+    /// The closest `f32`, which usually isn't exact
+    pub fn to_f32(&self) -> f32 {
+        self.to_f64() as f32
+    }
+
+    /// The closest `f64`, which usually isn't exact
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// The same value with `scale` digits after the point. Only makes sense going up, or when
+    /// the dropped digits are zero.
+    fn rescale(&self, scale: u32) -> Self {
+        if scale >= self.scale {
+            let mantissa = self
+                .mantissa
+                .checked_mul(pow10(scale - self.scale))
+                .expect("decimal overflow");
+            Decimal { mantissa, scale }
+        } else {
+            Decimal { mantissa: self.mantissa / pow10(self.scale - scale), scale }
+        }
+    }
+
+    /// (before the point, after the point as a mantissa at `scale`), both with the same sign
+    fn split(&self) -> (i128, i128) {
+        let divisor = pow10(self.scale);
+        (self.mantissa / divisor, self.mantissa % divisor)
+    }
+
+    /// Without trailing zeros after the point, so equal values look the same
+    fn normalized(&self) -> Self {
+        let mut d = *self;
+        while d.scale > 0 && d.mantissa % 10 == 0 {
+            d.mantissa /= 10;
+            d.scale -= 1;
+        }
+        d
+    }
+}
+
+fn pow10(exp: u32) -> i128 {
+    10i128.pow(exp)
+}
+
+impl Default for Decimal {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = &'static str;
+
+    /// Plain decimal notation, like `0.101`, `-1` or `.5`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (negative, digits) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (whole, fraction) = match digits.find('.') {
+            Some(point) => (&digits[..point], &digits[point + 1..]),
+            None => (digits, ""),
+        };
+        if whole.is_empty() && fraction.is_empty() {
+            return Err("decimal has no digits");
+        }
+        if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return Err("decimal has something other than digits");
+        }
+        if fraction.len() > MAX_SCALE as usize {
+            return Err("decimal has too many digits after the point");
+        }
+
+        let mut mantissa: i128 = 0;
+        for c in whole.chars().chain(fraction.chars()) {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(c.to_digit(10).unwrap() as i128))
+                .ok_or("decimal is too big")?;
+        }
+        if negative {
+            mantissa = -mantissa;
+        }
+        Ok(Decimal { mantissa, scale: fraction.len() as u32 })
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = self.mantissa.abs().to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, whole, fraction)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+/// Compares the whole parts, then what's after the point. Scaling a whole mantissa up to the
+/// other's scale can overflow, the part after the point never has more than 28 digits.
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (whole, fraction) = self.split();
+        let (other_whole, other_fraction) = other.split();
+        whole.cmp(&other_whole).then_with(|| {
+            let scale = self.scale.max(other.scale);
+            let fraction = fraction * pow10(scale - self.scale);
+            let other_fraction = other_fraction * pow10(scale - other.scale);
+            fraction.cmp(&other_fraction)
+        })
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normalized = self.normalized();
+        normalized.mantissa.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        let mantissa = self
+            .rescale(scale)
+            .mantissa
+            .checked_add(other.rescale(scale).mantissa)
+            .expect("decimal overflow");
+        Decimal { mantissa, scale }
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Decimal) -> Decimal {
+        self + -other
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal { mantissa: -self.mantissa, scale: self.scale }
+    }
+}
+
+/// Exact, `0.101 * 19.99` is `2.01899`
+impl Mul for Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: Decimal) -> Decimal {
+        let product = match self.mantissa.checked_mul(other.mantissa) {
+            Some(mantissa) => Decimal { mantissa, scale: self.scale + other.scale },
+            // Trailing zeros like in `2.000000` can be all that overflows
+            None => {
+                let (a, b) = (self.normalized(), other.normalized());
+                let mantissa = a.mantissa.checked_mul(b.mantissa).expect("decimal overflow");
+                Decimal { mantissa, scale: a.scale + b.scale }
+            }
+        };
+        if product.scale > MAX_SCALE {
+            product.normalized().round_dp(MAX_SCALE)
+        } else {
+            product
+        }
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(n: i64) -> Self {
+        Decimal::new(n as i128, 0)
+    }
+}
+
+impl From<Decimal> for f32 {
+    fn from(d: Decimal) -> f32 {
+        d.to_f32()
+    }
+}
+
+impl From<Decimal> for f64 {
+    fn from(d: Decimal) -> f64 {
+        d.to_f64()
+    }
+}
+
+/// As a string, like `"0.101"`, so nothing is lost going through a float
+#[cfg(feature = "serde")]
+impl serde::Serialize for Decimal {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Decimal {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(D::Error::custom)
+    }
+}
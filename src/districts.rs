@@ -0,0 +1,72 @@
+//! The special taxing districts DOR flags on an address. Being in one matters for motor vehicle
+//! and other special taxes, not for retail sales tax.
+
+use crate::Address;
+use std::fmt;
+
+/// Which special districts an address is in. None when DOR didn't say.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct SpecialDistricts {
+    /// Regional Transit Authority (Sound Transit)
+    pub rta: Option<bool>,
+    /// Public Transportation Benefit Area
+    pub ptba: Option<bool>,
+    /// Community Empowerment Zone
+    pub cez: Option<bool>,
+}
+
+/// DOR sent a flag value we don't know how to read
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnknownFlag {
+    /// Which attribute, `rta`, `ptba` or `cez`
+    pub attr: &'static str,
+    pub value: String,
+}
+
+impl fmt::Display for UnknownFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DOR sent {:?} for {}, expected Y or N", self.value, self.attr)
+    }
+}
+
+impl std::error::Error for UnknownFlag {}
+
+impl Address {
+    /// In a Regional Transit Authority?
+    pub fn in_rta(&self) -> Result<Option<bool>, UnknownFlag> {
+        flag("rta", &self.rta)
+    }
+
+    /// In a Public Transportation Benefit Area?
+    pub fn in_ptba(&self) -> Result<Option<bool>, UnknownFlag> {
+        flag("ptba", &self.ptba)
+    }
+
+    /// In a Community Empowerment Zone?
+    pub fn in_cez(&self) -> Result<Option<bool>, UnknownFlag> {
+        flag("cez", &self.cez)
+    }
+
+    /// All the flags at once, failing on the first one we can't read
+    pub fn special_districts(&self) -> Result<SpecialDistricts, UnknownFlag> {
+        Ok(SpecialDistricts {
+            rta: self.in_rta()?,
+            ptba: self.in_ptba()?,
+            cez: self.in_cez()?,
+        })
+    }
+}
+
+/// DOR sends `Y` or `N`, the longer spellings are accepted in case that changes
+fn flag(attr: &'static str, value: &Option<String>) -> Result<Option<bool>, UnknownFlag> {
+    let value = match value {
+        Some(value) => value.trim(),
+        None => return Ok(None),
+    };
+    match value.to_ascii_uppercase().as_str() {
+        "" => Ok(None),
+        "Y" | "YES" | "TRUE" | "1" => Ok(Some(true)),
+        "N" | "NO" | "FALSE" | "0" => Ok(Some(false)),
+        _ => Err(UnknownFlag { attr, value: value.to_string() }),
+    }
+}
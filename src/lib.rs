@@ -3,17 +3,47 @@
 //! It gets data from DOR using its [XML URL interface defined here](https://dor.wa.gov/find-taxes-rates/retail-sales-tax/destination-based-sales-tax-and-streamlined-sales-tax/wa-sales-tax-rate-lookup-url-interface).
 //! 
 //! Note that this needs [`tokio`](https://crates.io/crates/tokio), as [`reqwest`](https://crates.io/crates/reqwest) needs `tokio`!
+//!
+//! reqwest is behind the default `reqwest` feature. Without it, give [`ClientBuilder::transport`]
+//! something to make requests with, and the shortcuts that build a default client, like [`get`],
+//! aren't there.
 
 #[macro_use]
 extern crate log;
 
+pub mod batch;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cache;
+pub mod calc;
+mod client;
+mod coords;
+mod decimal;
+mod districts;
+mod location;
+mod normalize;
+pub mod offline;
+mod period;
+mod ratelimit;
+mod retry;
+pub mod transport;
+
+pub use client::{Client, ClientBuilder};
+pub use coords::Coordinates;
+pub use decimal::Decimal;
+pub use districts::{SpecialDistricts, UnknownFlag};
+pub use location::{County, LocationCode, Registry};
+pub use period::{Date, RatePeriod};
+pub use ratelimit::{RateLimiter, RateLimiterStats};
+pub use retry::{Attempt, RetryPolicy, RetryReport, StopReason};
+
+#[cfg(feature = "reqwest")]
 use reqwest::Error as ReqwestError;
+use std::borrow::Cow;
 use std::convert::TryFrom;
+use std::fmt;
+use std::time::Duration;
 use strong_xml::{XmlRead, XmlWrite};
-use url::form_urlencoded;
-
-
-const DOR_ADDR_PREFIX: &'static str = "https://webgis.dor.wa.gov/webapi/AddressRates.aspx?output=xml";
 
 /// These codes are taken from [the DOR spec](https://dor.wa.gov/find-taxes-rates/retail-sales-tax/destination-based-sales-tax-and-streamlined-sales-tax/wa-sales-tax-rate-lookup-url-interface);
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -43,6 +73,39 @@ impl Code {
     pub fn retryable(&self) -> bool {
         &Code::InternalError == self
     }
+
+    /// The variant's name, like `AddrFound`. Stays the same across releases, even if the
+    /// variant is renamed, so it's safe to store.
+    pub fn name(&self) -> &'static str {
+        use Code::*;
+        match self {
+            AddrFound => "AddrFound",
+            AddrNotFoundZipFound => "AddrNotFoundZipFound",
+            AdrrUpdatedAndFoundValidate => "AdrrUpdatedAndFoundValidate",
+            AddrUpdatedAndZipFoundValidate => "AddrUpdatedAndZipFoundValidate",
+            AddrCorrectedAndFoundValidate => "AddrCorrectedAndFoundValidate",
+            Zip5FoundNoAddrOrZip4 => "Zip5FoundNoAddrOrZip4",
+            NoAddrNoZips => "NoAddrNoZips",
+            InvalidLongLat => "InvalidLongLat",
+            InternalError => "InternalError",
+        }
+    }
+
+    /// DOR's number for a code, the inverse of `Code::try_from`
+    pub fn number(&self) -> u8 {
+        use Code::*;
+        match self {
+            AddrFound => 0,
+            AddrNotFoundZipFound => 1,
+            AdrrUpdatedAndFoundValidate => 2,
+            AddrUpdatedAndZipFoundValidate => 3,
+            AddrCorrectedAndFoundValidate => 4,
+            Zip5FoundNoAddrOrZip4 => 5,
+            NoAddrNoZips => 6,
+            InvalidLongLat => 7,
+            InternalError => 9,
+        }
+    }
 }
 
 use std::str::FromStr;
@@ -55,6 +118,13 @@ impl FromStr for Code {
     }
 }
 
+/// DOR's number, like `0`, the same as in the XML
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.number())
+    }
+}
+
 impl TryFrom<u8> for Code {
     type Error = &'static str;
 
@@ -75,30 +145,141 @@ impl TryFrom<u8> for Code {
     }
 }
 
+/// DOR's number for the code
+impl From<Code> for u8 {
+    fn from(code: Code) -> u8 {
+        code.number()
+    }
+}
+
+/// How a `Code` looks when serialized, `{"name": "AddrFound", "value": 0}`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Code")]
+struct CodeRepr {
+    name: String,
+    value: u8,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Code {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CodeRepr { name: self.name().to_string(), value: self.number() }.serialize(serializer)
+    }
+}
+
+/// The value decides the code, the name has to agree with it
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Code {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let repr = CodeRepr::deserialize(deserializer)?;
+        let code = Code::try_from(repr.value).map_err(D::Error::custom)?;
+        if code.name() != repr.name {
+            return Err(D::Error::custom("code name does not match its value"));
+        }
+        Ok(code)
+    }
+}
+
 /// Error retreiving tax info. DOR errors most likely mean bad input, as in a weird address
+///
+/// More variants can be added without a major release, so matches need a `_` arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum TaxInfoError {
+    #[cfg(feature = "reqwest")]
     Http(ReqwestError),
+    /// A transport other than reqwest couldn't get a response
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    /// DOR answered with something other than 2xx. `body` is whatever it sent back
+    Status { status: u16, body: String },
+    /// An attempt took longer than this
+    Timeout(Duration),
+    /// DOR answered 2xx, but not with XML, like an HTML maintenance page
+    NotXml(String),
+    /// DOR sent XML we couldn't make a TaxInfo out of. `reason` is what the parser said
+    Schema { reason: String, body: String },
     /// DOR gave a code that means there as an error. We return the raw TaxInfo object in case
     /// you'd like to inspect it
     Dor((Code, TaxInfo)),
     Internal(&'static str),
-    NoMoreRetries,
+    /// Every attempt failed with something worth retrying, and the retry policy said to stop. The
+    /// report has each attempt's error and timings.
+    NoMoreRetries(Box<RetryReport>),
+    /// The point isn't anywhere near WA, so no request was made
+    OutsideWashington(Coordinates),
+    /// DOR said the latitude/longitude was invalid (code 7)
+    InvalidLongLat(TaxInfo),
 }
 
 impl TaxInfoError {
     pub fn retryable(&self) -> bool {
         match self {
-            TaxInfoError::NoMoreRetries => false,
+            TaxInfoError::NoMoreRetries(_) => false,
             TaxInfoError::Dor((code,  _)) => code.retryable(),
+            #[cfg(feature = "reqwest")]
             TaxInfoError::Http(re) => re.status().map(|s| {
                 s.is_server_error()
             }).unwrap_or(true),
+            TaxInfoError::Transport(_) => true,
+            TaxInfoError::Status { status, .. } => *status >= 500 || *status == 429,
+            TaxInfoError::Timeout(_) => true,
+            TaxInfoError::NotXml(_) => true,
+            TaxInfoError::Schema { .. } => false,
             TaxInfoError::Internal(_) => false,
+            TaxInfoError::OutsideWashington(_) => false,
+            TaxInfoError::InvalidLongLat(_) => false,
+        }
+    }
+
+    /// The raw response body, for the errors that have one
+    pub fn body(&self) -> Option<&str> {
+        match self {
+            TaxInfoError::Status { body, .. } => Some(body),
+            TaxInfoError::NotXml(body) => Some(body),
+            TaxInfoError::Schema { body, .. } => Some(body),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TaxInfoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "reqwest")]
+            TaxInfoError::Http(re) => write!(f, "http error talking to DOR: {}", re),
+            TaxInfoError::Transport(e) => write!(f, "error talking to DOR: {}", e),
+            TaxInfoError::Status { status, .. } => write!(f, "DOR answered with http status {}", status),
+            TaxInfoError::Timeout(timeout) => write!(f, "DOR took longer than {:?} to answer", timeout),
+            TaxInfoError::NotXml(_) => write!(f, "DOR's response was not XML"),
+            TaxInfoError::Schema { reason, .. } => write!(f, "DOR's XML did not look like a tax rate response: {}", reason),
+            TaxInfoError::Dor((code, _)) => write!(f, "DOR returned error code {:?}", code),
+            TaxInfoError::Internal(reason) => write!(f, "{}", reason),
+            TaxInfoError::NoMoreRetries(report) => write!(f, "{}", report),
+            TaxInfoError::OutsideWashington(coords) => {
+                write!(f, "{}, {} is not in WA", coords.lat, coords.lng)
+            }
+            TaxInfoError::InvalidLongLat(_) => write!(f, "DOR said the latitude/longitude was invalid"),
+        }
+    }
+}
+
+impl std::error::Error for TaxInfoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "reqwest")]
+            TaxInfoError::Http(re) => Some(re),
+            TaxInfoError::Transport(e) => Some(e.as_ref()),
+            TaxInfoError::NoMoreRetries(report) => report
+                .last_error()
+                .map(|e| e as &(dyn std::error::Error + 'static)),
+            _ => None,
         }
     }
 }
 
+#[cfg(feature = "reqwest")]
 impl From<ReqwestError> for TaxInfoError {
     fn from(re: ReqwestError) -> Self {
         Self::Http(re)
@@ -107,23 +288,31 @@ impl From<ReqwestError> for TaxInfoError {
 
 
 /// The Address parsed by DOR, returned as part of TaxInfo
-#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+///
+/// Fields are in the order DOR sends the attributes, which is the order they're written in.
+#[derive(XmlWrite, XmlRead, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[xml(tag = "addressline")]
 pub struct Address {
-    #[xml(attr = "househigh")]
-    pub househigh: Option<u32>,
     #[xml(attr = "houselow")]
     pub houselow: Option<u32>,
+    #[xml(attr = "househigh")]
+    pub househigh: Option<u32>,
     #[xml(attr = "evenodd")]
     pub evenodd: Option<String>,
     #[xml(attr = "street")]
     pub street: Option<String>,
+    #[xml(attr = "state")]
+    pub state: Option<String>,
     #[xml(attr = "zip")]
     pub zip: Option<u32>,
     #[xml(attr = "plus4")]
     pub plus4: Option<u32>,
     #[xml(attr = "period")]
     pub period: Option<String>,
+    /// The location code of the address range, usually the same as `TaxInfo::loccode`
+    #[xml(attr = "code")]
+    pub code: Option<String>,
     #[xml(attr = "rta")]
     pub rta: Option<String>,
     #[xml(attr = "ptba")]
@@ -132,35 +321,114 @@ pub struct Address {
     pub cez: Option<String>,
 }
 
+impl Address {
+    /// `period` parsed, None when it's missing or isn't something like `Q32020`. The raw string
+    /// is still in `period`.
+    pub fn rate_period(&self) -> Option<RatePeriod> {
+        self.period.as_ref().and_then(|p| p.parse().ok())
+    }
+
+    /// As an `addressline` element, for storing on its own
+    pub(crate) fn to_xml(&self) -> String {
+        self.to_string().expect("writing XML to a string can't fail")
+    }
+
+    pub(crate) fn from_xml(xml: &str) -> strong_xml::XmlResult<Address> {
+        let mut address = Address::from_str(xml)?;
+        address.unescape()?;
+        Ok(address)
+    }
+
+    fn unescape(&mut self) -> strong_xml::XmlResult<()> {
+        for value in [
+            &mut self.evenodd,
+            &mut self.street,
+            &mut self.state,
+            &mut self.period,
+            &mut self.code,
+            &mut self.rta,
+            &mut self.ptba,
+            &mut self.cez,
+        ]
+        .iter_mut()
+        {
+            if let Some(value) = value.as_mut() {
+                unescape(value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Tax Rate information, returned as part of TaxInfo
-#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[derive(XmlWrite, XmlRead, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[xml(tag = "rate")]
 pub struct TaxRate {
     #[xml(attr = "name")]
     pub name: String,
     #[xml(attr = "code")]
     pub code: String,
-    #[xml(attr = "localrate")]
-    pub localrate: f32,
     #[xml(attr = "staterate")]
-    pub staterate: f32,
+    pub staterate: Decimal,
+    #[xml(attr = "localrate")]
+    pub localrate: Decimal,
+}
+
+impl TaxRate {
+    /// The local part of this jurisdiction's rate, rounded to the nearest `f32`
+    pub fn localrate_f32(&self) -> f32 {
+        self.localrate.to_f32()
+    }
+
+    /// The state's 6.5%, rounded to the nearest `f32`. Keep the `Decimal` for anything that
+    /// gets added up.
+    pub fn staterate_f32(&self) -> f32 {
+        self.staterate.to_f32()
+    }
+
+    /// As a `rate` element, for storing on its own
+    pub(crate) fn to_xml(&self) -> String {
+        self.to_string().expect("writing XML to a string can't fail")
+    }
+
+    pub(crate) fn from_xml(xml: &str) -> strong_xml::XmlResult<TaxRate> {
+        let mut taxrate = TaxRate::from_str(xml)?;
+        taxrate.unescape()?;
+        Ok(taxrate)
+    }
+
+    fn unescape(&mut self) -> strong_xml::XmlResult<()> {
+        unescape(&mut self.name)?;
+        unescape(&mut self.code)
+    }
+}
+
+/// strong-xml escapes attributes when writing but hands them back raw when reading, so anything
+/// read goes through here to get `&amp;` and friends back to what they stand for.
+fn unescape(value: &mut String) -> strong_xml::XmlResult<()> {
+    if let Cow::Owned(unescaped) = strong_xml::utils::xml_unescape(value)? {
+        *value = unescaped;
+    }
+    Ok(())
 }
 
 
 /// Tax Info provided by WA State DOR
 /// 
 /// See [the DOR website](https://dor.wa.gov/find-taxes-rates/retail-sales-tax/destination-based-sales-tax-and-streamlined-sales-tax/wa-sales-tax-rate-lookup-url-interface) for specifics.
-#[derive(XmlRead, PartialEq, Debug)]
+#[derive(XmlWrite, XmlRead, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[xml(tag = "response")]
 pub struct TaxInfo {
     #[xml(attr = "loccode")]
     pub loccode: i32,
+    #[xml(attr = "localrate")]
+    pub localrate: Decimal,
     #[xml(attr = "rate")]
-    pub rate: f32,
+    pub rate: Decimal,
     #[xml(attr = "code")]
     pub code: Code,
-    #[xml(attr = "localrate")]
-    pub localrate: f32,
     #[xml(attr = "debughint")]
     pub debughint: Option<String>,
     // Children
@@ -170,48 +438,105 @@ pub struct TaxInfo {
     pub taxrate: Option<TaxRate>,
 }
 
-const MAX_ATTEMPTS: usize = 3;
-const DEFAULT_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(7_000);
+impl TaxInfo {
+    /// The combined state and local rate, for display. Compute tax from `rate` itself, most
+    /// rates have no exact `f32`.
+    pub fn rate_f32(&self) -> f32 {
+        self.rate.to_f32()
+    }
 
-/// Has retries, reasonable timeouts, defaults, fully ready to go.
-pub async fn get(addr: &str, city: &str, zip: &str) -> Result<TaxInfo, TaxInfoError> {
-    let mut remaining_attempts = MAX_ATTEMPTS;
-    while remaining_attempts > 0 {
-        remaining_attempts -= 1;
-        match tokio::time::timeout(DEFAULT_TIMEOUT, get_basic(addr, city, zip)).await {
-            Ok(Ok(r)) => return Ok(r),
-            Ok(Err(e)) => if !e.retryable() {
-                return Err(e);
-            }
-            Err(_) => {
-                // continue
-            }
+    /// Just the local share of `rate`, rounded to the nearest `f32`
+    pub fn localrate_f32(&self) -> f32 {
+        self.localrate.to_f32()
+    }
+
+    /// Parses DOR's XML response. Unlike a lookup, an error `Code` isn't an error here, the
+    /// `TaxInfo` comes back as is.
+    pub fn from_xml(xml: &str) -> Result<TaxInfo, TaxInfoError> {
+        if !xml.trim_start().starts_with('<') {
+            return Err(TaxInfoError::NotXml(xml.to_string()));
+        }
+        // XmlError isn't Send and doesn't implement Error, so keep what it says
+        TaxInfo::from_str(xml).and_then(TaxInfo::unescaped).map_err(|e| TaxInfoError::Schema {
+            reason: format!("{:?}", e),
+            body: xml.to_string(),
+        })
+    }
+
+    fn unescaped(mut self) -> strong_xml::XmlResult<TaxInfo> {
+        if let Some(debughint) = self.debughint.as_mut() {
+            unescape(debughint)?;
+        }
+        if let Some(address) = self.address.as_mut() {
+            address.unescape()?;
+        }
+        if let Some(taxrate) = self.taxrate.as_mut() {
+            taxrate.unescape()?;
         }
+        Ok(self)
+    }
+
+    /// Back to XML in DOR's schema, which `from_xml` parses to an equal `TaxInfo`. Attributes
+    /// are in DOR's order and rates keep the digits DOR sent, so a response DOR sent comes back
+    /// out byte for byte. Values with `&`, `<` or quotes in them are escaped with the named
+    /// entities (`&amp;`, `&apos;`, ...), which `from_xml` turns back into the characters.
+    pub fn to_xml(&self) -> String {
+        let xml = self.to_string().expect("writing XML to a string can't fail");
+        // DOR closes empty elements with ` />`. A `"` inside a value is escaped, so `"/>` is
+        // always the end of an element.
+        xml.replace("\"/>", "\" />")
+    }
+
+    /// The rate period these rates are for, from the `period` DOR sent with the address. None
+    /// when there's no address, or DOR sent a period we don't understand.
+    pub fn effective_period(&self) -> Option<RatePeriod> {
+        self.address.as_ref().and_then(Address::rate_period)
+    }
+}
+
+/// Turns DOR's raw XML into a TaxInfo, treating error codes as errors
+pub(crate) fn parse_response(raw_string: &str) -> Result<TaxInfo, TaxInfoError> {
+    let rti = TaxInfo::from_xml(raw_string)?;
+    if rti.code == Code::InvalidLongLat {
+        Err(TaxInfoError::InvalidLongLat(rti))
+    } else if rti.code.is_error() {
+        Err(TaxInfoError::Dor((rti.code, rti)))
+    } else {
+        Ok(rti)
     }
-    Err(TaxInfoError::NoMoreRetries)
+}
+
+/// Has retries, reasonable timeouts, defaults, fully ready to go.
+///
+/// Builds a fresh default [`Client`] for every call, hold onto a [`Client`] yourself to reuse
+/// connections.
+#[cfg(feature = "reqwest")]
+pub async fn get(addr: &str, city: &str, zip: &str) -> Result<TaxInfo, TaxInfoError> {
+    Client::builder().build()?.get(addr, city, zip).await
 }
 
 /// No retries, just one attempt, no timeout, nothing
+#[cfg(feature = "reqwest")]
 pub async fn get_basic(addr: &str, city: &str, zip: &str) -> Result<TaxInfo, TaxInfoError> {
-    let request: String = form_urlencoded::Serializer::new(DOR_ADDR_PREFIX.to_string())
-        .append_pair("addr", addr)
-        .append_pair("city", city)
-        .append_pair("zip", zip)
-        .finish();
-
-    debug!("URL to GET from dor {}", request);
-    let raw_string = reqwest::get(&request).await?.text().await?;
-
-    debug!("raw string from DOR {}", raw_string);
-
-    match TaxInfo::from_str(&raw_string) {
-        Ok(rti) => {
-            if rti.code.is_error() {
-                Err(TaxInfoError::Dor((rti.code, rti)))
-            } else {
-                Ok(rti)
-            }
-        }
-        Err(_e) => Err(TaxInfoError::Internal("Error parsing response from DOR")),
-    }
-}
\ No newline at end of file
+    Client::builder().build()?.get_basic(addr, city, zip).await
+}
+
+/// Has retries, reasonable timeouts, defaults, fully ready to go. Looks up by point instead of
+/// address.
+#[cfg(feature = "reqwest")]
+pub async fn get_by_coords(lat: f64, lng: f64) -> Result<TaxInfo, TaxInfoError> {
+    Client::builder().build()?.get_by_coords(lat, lng).await
+}
+
+/// No retries, just one attempt, no timeout, nothing. Looks up by point instead of address.
+#[cfg(feature = "reqwest")]
+pub async fn get_by_coords_basic(lat: f64, lng: f64) -> Result<TaxInfo, TaxInfoError> {
+    Client::builder().build()?.get_by_coords_basic(lat, lng).await
+}
+
+/// Has retries, reasonable timeouts, defaults, fully ready to go. Gets the rates that applied on
+/// `date` rather than today's.
+#[cfg(feature = "reqwest")]
+pub async fn get_as_of(addr: &str, city: &str, zip: &str, date: Date) -> Result<TaxInfo, TaxInfoError> {
+    Client::builder().build()?.get_as_of(addr, city, zip, date).await
+}
@@ -0,0 +1,187 @@
+//! Location codes, and what's in them.
+//!
+//! A WA location code is four digits. The first two are the county, numbered alphabetically from
+//! `01` Adams to `39` Yakima, and the last two are the city within it, with `00` for the
+//! unincorporated parts of the county. Seattle is `1726`, King County is `17`.
+
+use crate::normalize::normalize;
+use crate::offline::{OfflineRate, RateTable};
+use crate::{TaxInfo, TaxRate};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// Every county, in location code order
+const COUNTIES: [&str; 39] = [
+    "Adams", "Asotin", "Benton", "Chelan", "Clallam", "Clark", "Columbia", "Cowlitz", "Douglas",
+    "Ferry", "Franklin", "Garfield", "Grant", "Grays Harbor", "Island", "Jefferson", "King",
+    "Kitsap", "Kittitas", "Klickitat", "Lewis", "Lincoln", "Mason", "Okanogan", "Pacific",
+    "Pend Oreille", "Pierce", "San Juan", "Skagit", "Skamania", "Snohomish", "Spokane", "Stevens",
+    "Thurston", "Wahkiakum", "Walla Walla", "Whatcom", "Whitman", "Yakima",
+];
+
+/// A WA location code, like `1726` for Seattle
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct LocationCode(u16);
+
+impl LocationCode {
+    /// None unless the first two digits are a county
+    pub fn new(code: u16) -> Option<Self> {
+        if code >= 100 && code / 100 <= COUNTIES.len() as u16 {
+            Some(LocationCode(code))
+        } else {
+            None
+        }
+    }
+
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+
+    pub fn county(&self) -> County {
+        County((self.0 / 100) as u8)
+    }
+
+    /// The last two digits, the city within the county, 0 for unincorporated
+    pub fn jurisdiction(&self) -> u8 {
+        (self.0 % 100) as u8
+    }
+
+    /// True for the parts of a county that aren't in any city
+    pub fn is_unincorporated(&self) -> bool {
+        self.jurisdiction() == 0
+    }
+}
+
+/// Four digits, `0101`
+impl fmt::Display for LocationCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}", self.0)
+    }
+}
+
+impl TryFrom<i32> for LocationCode {
+    type Error = &'static str;
+
+    fn try_from(code: i32) -> Result<Self, Self::Error> {
+        u16::try_from(code)
+            .ok()
+            .and_then(Self::new)
+            .ok_or("location code is not a WA county and city")
+    }
+}
+
+impl FromStr for LocationCode {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code: i32 = s.trim().parse().map_err(|_| "location code is not a number")?;
+        Self::try_from(code)
+    }
+}
+
+/// A WA county, numbered like location codes
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct County(u8);
+
+impl County {
+    /// None unless it's 1 through 39
+    pub fn new(number: u8) -> Option<Self> {
+        if number >= 1 && number as usize <= COUNTIES.len() {
+            Some(County(number))
+        } else {
+            None
+        }
+    }
+
+    pub fn number(&self) -> u8 {
+        self.0
+    }
+
+    /// Like `King`, without "County"
+    pub fn name(&self) -> &'static str {
+        COUNTIES[self.0 as usize - 1]
+    }
+
+    /// The location code for the unincorporated parts of the county
+    pub fn unincorporated(&self) -> LocationCode {
+        LocationCode(self.0 as u16 * 100)
+    }
+
+    pub fn all() -> impl Iterator<Item = County> {
+        (1..=COUNTIES.len() as u8).map(County)
+    }
+}
+
+impl fmt::Display for County {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} County", self.name())
+    }
+}
+
+/// Names for location codes, as DOR spells them in `<rate name=...>`.
+///
+/// [`Registry::new`] is empty, so [`Registry::check`] has nothing to say until names are loaded
+/// from DOR's rate files with [`Registry::from_rate_table`], or added with [`Registry::insert`].
+/// Names aren't made up for codes DOR hasn't named, they wouldn't match what DOR sends.
+#[derive(Clone, Debug)]
+pub struct Registry {
+    names: HashMap<LocationCode, String>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry { names: HashMap::new() }
+    }
+
+    /// Every name in the rate table. Where the table has more than one period for a code, the
+    /// latest name wins.
+    pub fn from_rate_table(rates: &RateTable) -> Self {
+        let mut registry = Self::new();
+        let mut named: Vec<&OfflineRate> = rates.iter().filter(|r| !r.name.is_empty()).collect();
+        named.sort_by_key(|r| r.period);
+        for rate in named {
+            if let Ok(code) = LocationCode::try_from(rate.loccode) {
+                registry.insert(code, rate.name.clone());
+            }
+        }
+        registry
+    }
+
+    pub fn insert(&mut self, code: LocationCode, name: impl Into<String>) {
+        self.names.insert(code, name.into());
+    }
+
+    pub fn name(&self, code: LocationCode) -> Option<&str> {
+        self.names.get(&code).map(|name| name.as_str())
+    }
+
+    /// Whether the name DOR sent matches the registry, ignoring case and punctuation. None when
+    /// the registry doesn't know the code.
+    pub fn check(&self, taxrate: &TaxRate) -> Option<bool> {
+        let code: LocationCode = taxrate.code.parse().ok()?;
+        self.name(code).map(|name| normalize(name) == normalize(&taxrate.name))
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaxInfo {
+    /// `loccode` as a `LocationCode`, None when it isn't one, like the `-1` DOR sends with errors
+    pub fn location_code(&self) -> Option<LocationCode> {
+        LocationCode::try_from(self.loccode).ok()
+    }
+}
@@ -0,0 +1,91 @@
+//! Puts addresses into one canonical spelling, so `400 Broad Street` and `400  broad st.` compare
+//! equal.
+
+/// Long forms to USPS abbreviations, for the words that commonly show up in WA addresses
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("STREET", "ST"),
+    ("AVENUE", "AVE"),
+    ("AV", "AVE"),
+    ("ROAD", "RD"),
+    ("DRIVE", "DR"),
+    ("BOULEVARD", "BLVD"),
+    ("LANE", "LN"),
+    ("COURT", "CT"),
+    ("PLACE", "PL"),
+    ("PARKWAY", "PKWY"),
+    ("HIGHWAY", "HWY"),
+    ("TERRACE", "TER"),
+    ("CIRCLE", "CIR"),
+    ("NORTH", "N"),
+    ("SOUTH", "S"),
+    ("EAST", "E"),
+    ("WEST", "W"),
+    ("NORTHEAST", "NE"),
+    ("NORTHWEST", "NW"),
+    ("SOUTHEAST", "SE"),
+    ("SOUTHWEST", "SW"),
+    ("APARTMENT", "APT"),
+    ("SUITE", "STE"),
+    ("MOUNT", "MT"),
+    ("FORT", "FT"),
+];
+
+/// Words that don't distinguish one street from another very well, dropped by [`street_core`]
+const DIRECTIONALS_AND_SUFFIXES: &[&str] = &[
+    "N", "S", "E", "W", "NE", "NW", "SE", "SW", "ST", "AVE", "RD", "DR", "BLVD", "LN", "CT", "PL",
+    "PKWY", "HWY", "TER", "CIR", "WAY", "LOOP",
+];
+
+/// Uppercases, drops punctuation, collapses whitespace and abbreviates common words
+pub(crate) fn normalize(s: &str) -> String {
+    let cleaned: String = s
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '#' { c.to_ascii_uppercase() } else { ' ' })
+        .collect();
+    cleaned
+        .split_whitespace()
+        .map(|word| {
+            ABBREVIATIONS
+                .iter()
+                .find(|(long, _)| *long == word)
+                .map(|(_, short)| *short)
+                .unwrap_or(word)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Splits `400 Broad St` into the house number and the normalized street, `(Some(400), "BROAD ST")`
+pub(crate) fn split_house_number(addr: &str) -> (Option<u32>, String) {
+    let normalized = normalize(addr);
+    let mut words = normalized.splitn(2, ' ');
+    let first = words.next().unwrap_or("");
+    match first.parse() {
+        Ok(number) => (Some(number), words.next().unwrap_or("").to_string()),
+        Err(_) => (None, normalized),
+    }
+}
+
+/// A normalized street without directionals or suffixes, `NE 45TH ST` becomes `45TH`. Used when
+/// an exact match fails.
+pub(crate) fn street_core(normalized_street: &str) -> String {
+    let core: Vec<&str> = normalized_street
+        .split(' ')
+        .filter(|word| !DIRECTIONALS_AND_SUFFIXES.contains(word))
+        .collect();
+    if core.is_empty() {
+        normalized_street.to_string()
+    } else {
+        core.join(" ")
+    }
+}
+
+/// The 5 digit zip and optional plus 4 from `98109`, `98109-4607` or `981094607`
+pub(crate) fn split_zip(zip: &str) -> Option<(u32, Option<u32>)> {
+    let digits: String = zip.chars().filter(|c| c.is_ascii_digit()).collect();
+    match digits.len() {
+        5 => Some((digits.parse().ok()?, None)),
+        9 => Some((digits[..5].parse().ok()?, Some(digits[5..].parse().ok()?))),
+        _ => None,
+    }
+}
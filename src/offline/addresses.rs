@@ -0,0 +1,175 @@
+//! DOR's address range boundary files, mapping ranges of house numbers on a street to location
+//! codes.
+
+use super::{column, csv_files, OfflineError};
+use crate::normalize::{normalize, street_core};
+use crate::Address;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// One row of a boundary file, a range of houses on one side (or both sides) of a street
+#[derive(Clone, PartialEq, Debug)]
+pub struct AddressRange {
+    pub loccode: i32,
+    /// The range, in the same shape DOR returns as part of TaxInfo. `street` is normalized.
+    pub address: Address,
+    street_core: String,
+}
+
+impl AddressRange {
+    /// True when the house number is in the range, and on the right side of the street
+    pub fn contains_house(&self, house: u32) -> bool {
+        let low = self.address.houselow.unwrap_or(0);
+        let high = self.address.househigh.unwrap_or(u32::max_value());
+        let side = match self.address.evenodd.as_deref() {
+            Some("E") => house % 2 == 0,
+            Some("O") => house % 2 == 1,
+            _ => true,
+        };
+        low <= house && house <= high && side
+    }
+
+    pub fn street(&self) -> &str {
+        self.address.street.as_deref().unwrap_or("")
+    }
+
+    pub(crate) fn street_core(&self) -> &str {
+        &self.street_core
+    }
+}
+
+/// Address ranges from DOR boundary files, indexed by 5 digit zip.
+///
+/// The files are csv with a header row. Columns are found by name, either the attribute names
+/// DOR uses in its XML (`houselow`, `househigh`, `evenodd`, `street`, `zip`, `plus4`, `period`,
+/// `code`, `rta`, `ptba`, `cez`) or the longer names from the SST boundary files
+/// (`Low Address Range`, `Odd/Even Indicator`, `Street Name`, ...). Pre and post directionals
+/// and street suffixes in their own columns are joined onto the street.
+#[derive(Clone, Debug, Default)]
+pub struct AddressTable {
+    by_zip: HashMap<u32, Vec<AddressRange>>,
+    /// Where each normalized street's ranges are in `by_zip`, as (zip, index)
+    by_street: HashMap<String, Vec<(u32, usize)>>,
+}
+
+impl AddressTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every csv file in a directory
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, OfflineError> {
+        let mut table = Self::new();
+        for file in csv_files(dir.as_ref())? {
+            table.load_file(file)?;
+        }
+        Ok(table)
+    }
+
+    /// Adds the ranges in one file. Returns how many rows were loaded.
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<usize, OfflineError> {
+        let path = path.as_ref();
+        let bad = |reason| OfflineError::Format(path.to_path_buf(), reason);
+
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_path(path)?;
+        let headers = reader.headers()?.clone();
+
+        let low_col = column(&headers, &["houselow", "Low Address Range", "Address Low"]);
+        let high_col = column(&headers, &["househigh", "High Address Range", "Address High"]);
+        let evenodd_col = column(&headers, &["evenodd", "Odd/Even Indicator", "Odd Even"]);
+        let pre_col = column(&headers, &["Street Pre-Directional", "Pre Directional"]);
+        let street_col = column(&headers, &["street", "Street Name"])
+            .ok_or_else(|| bad("no street column"))?;
+        let suffix_col = column(&headers, &["Street Suffix", "Suffix"]);
+        let post_col = column(&headers, &["Street Post-Directional", "Post Directional"]);
+        let zip_col = column(&headers, &["zip", "Zip Code", "Zip5"])
+            .ok_or_else(|| bad("no zip column"))?;
+        let plus4_col = column(&headers, &["plus4", "Plus 4", "Zip4"]);
+        let period_col = column(&headers, &["period"]);
+        let code_col = column(&headers, &["code", "loccode", "Location Code"])
+            .ok_or_else(|| bad("no location code column"))?;
+        let rta_col = column(&headers, &["rta"]);
+        let ptba_col = column(&headers, &["ptba"]);
+        let cez_col = column(&headers, &["cez"]);
+
+        let mut loaded = 0;
+        for record in reader.records() {
+            let record = record?;
+            let field = |col: Option<usize>| {
+                col.and_then(|col| record.get(col))
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.to_string())
+            };
+            let number = |col: Option<usize>, reason| -> Result<Option<u32>, OfflineError> {
+                match field(col) {
+                    Some(v) => v.parse().map(Some).map_err(|_| bad(reason)),
+                    None => Ok(None),
+                }
+            };
+
+            let zip = match number(Some(zip_col), "zip is not a number")? {
+                Some(zip) => zip,
+                // Blank lines and footers
+                None => continue,
+            };
+            let loccode: i32 = field(Some(code_col))
+                .ok_or_else(|| bad("row without a location code"))?
+                .parse()
+                .map_err(|_| bad("location code is not a number"))?;
+
+            let street_parts: Vec<String> = [pre_col, Some(street_col), suffix_col, post_col]
+                .iter()
+                .filter_map(|col| field(*col))
+                .collect();
+            let street = normalize(&street_parts.join(" "));
+
+            let address = Address {
+                houselow: number(low_col, "house low is not a number")?,
+                househigh: number(high_col, "house high is not a number")?,
+                evenodd: field(evenodd_col).map(|v| v.to_ascii_uppercase()),
+                street: Some(street.clone()),
+                state: Some("WA".to_string()),
+                zip: Some(zip),
+                plus4: number(plus4_col, "plus 4 is not a number")?,
+                period: field(period_col),
+                code: Some(loccode.to_string()),
+                rta: field(rta_col),
+                ptba: field(ptba_col),
+                cez: field(cez_col),
+            };
+            let in_zip = self.by_zip.entry(zip).or_insert_with(Vec::new);
+            self.by_street.entry(street.clone()).or_insert_with(Vec::new).push((zip, in_zip.len()));
+            in_zip.push(AddressRange { loccode, address, street_core: street_core(&street) });
+            loaded += 1;
+        }
+
+        debug!("loaded {} address ranges from {}", loaded, path.display());
+        Ok(loaded)
+    }
+
+    /// Every range in a 5 digit zip
+    pub fn in_zip(&self, zip: u32) -> &[AddressRange] {
+        self.by_zip.get(&zip).map(|ranges| ranges.as_slice()).unwrap_or(&[])
+    }
+
+    /// Every range, in any zip, on a normalized street that contains the house number
+    pub fn on_street(&self, house: u32, street: &str) -> impl Iterator<Item = &AddressRange> {
+        self.by_street
+            .get(street)
+            .into_iter()
+            .flatten()
+            .map(move |(zip, index)| &self.by_zip[zip][*index])
+            .filter(move |range| range.contains_house(house))
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_zip.values().map(|ranges| ranges.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_zip.is_empty()
+    }
+}
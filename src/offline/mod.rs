@@ -0,0 +1,88 @@
+//! Answers from DOR's downloadable files instead of its web service, for when the network isn't
+//! something you can count on.
+//!
+//! DOR publishes these files every quarter. Nothing in here makes a request.
+
+mod addresses;
+mod rates;
+mod resolve;
+mod zip4;
+
+pub use addresses::{AddressRange, AddressTable};
+pub use rates::{OfflineRate, RateTable};
+pub use resolve::Resolver;
+pub use zip4::{Zip4Index, Zip4Match, Zip4Range};
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Error loading DOR files
+#[derive(Debug)]
+pub enum OfflineError {
+    Io(std::io::Error),
+    Csv(csv::Error),
+    /// The file didn't look like what we expected, and why
+    Format(PathBuf, &'static str),
+}
+
+impl fmt::Display for OfflineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OfflineError::Io(e) => write!(f, "{}", e),
+            OfflineError::Csv(e) => write!(f, "bad csv: {}", e),
+            OfflineError::Format(path, reason) => write!(f, "{}: {}", path.display(), reason),
+        }
+    }
+}
+
+impl std::error::Error for OfflineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OfflineError::Io(e) => Some(e),
+            OfflineError::Csv(e) => Some(e),
+            OfflineError::Format(..) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for OfflineError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<csv::Error> for OfflineError {
+    fn from(e: csv::Error) -> Self {
+        Self::Csv(e)
+    }
+}
+
+/// The csv files in a directory, sorted so loading is deterministic
+fn csv_files(dir: &Path) -> Result<Vec<PathBuf>, OfflineError> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_csv = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("csv") || ext.eq_ignore_ascii_case("txt"))
+            .unwrap_or(false);
+        if path.is_file() && is_csv {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Finds the column whose header matches one of the names, ignoring case and spacing
+fn column(headers: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    let squash = |s: &str| -> String {
+        s.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    };
+    headers
+        .iter()
+        .position(|h| names.iter().any(|name| squash(h) == squash(name)))
+}
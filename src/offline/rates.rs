@@ -0,0 +1,172 @@
+//! DOR's quarterly rate files, mapping location codes to rates.
+
+use super::{column, csv_files, OfflineError};
+use crate::{Date, Decimal, RatePeriod, TaxRate};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A location code's rates for one period, as found in a DOR rate file
+#[derive(Clone, PartialEq, Debug)]
+pub struct OfflineRate {
+    pub loccode: i32,
+    pub period: RatePeriod,
+    /// Jurisdiction name, e.g. `SEATTLE`
+    pub name: String,
+    /// Combined state and local rate, what `TaxInfo::rate` would be
+    pub rate: Decimal,
+    pub localrate: Decimal,
+    pub staterate: Decimal,
+}
+
+impl OfflineRate {
+    /// The same shape DOR returns as part of TaxInfo
+    pub fn to_tax_rate(&self) -> TaxRate {
+        TaxRate {
+            name: self.name.clone(),
+            code: self.loccode.to_string(),
+            localrate: self.localrate,
+            staterate: self.staterate,
+        }
+    }
+}
+
+/// Rates keyed by location code and period, loaded from DOR rate files.
+///
+/// The files are csv with a header row, DOR's columns are `Location`, `Location Code`,
+/// `Local Rate`, `State Rate`, `Combined Sales Tax`, `Effective Date` and `Expiration Date`. The
+/// period comes from `Effective Date`, or from the file name (like `Rates_2020Q3.csv`) if that
+/// column is missing.
+#[derive(Clone, Debug, Default)]
+pub struct RateTable {
+    rates: HashMap<(i32, RatePeriod), OfflineRate>,
+}
+
+impl RateTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every csv file in a directory
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, OfflineError> {
+        let mut table = Self::new();
+        for file in csv_files(dir.as_ref())? {
+            table.load_file(file)?;
+        }
+        Ok(table)
+    }
+
+    /// Adds the rates in one file, replacing any already loaded for the same code and period.
+    /// Returns how many rows were loaded.
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<usize, OfflineError> {
+        let path = path.as_ref();
+        let bad = |reason| OfflineError::Format(path.to_path_buf(), reason);
+
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_path(path)?;
+        let headers = reader.headers()?.clone();
+
+        let name_col = column(&headers, &["Location", "Name", "Jurisdiction"]);
+        let code_col = column(&headers, &["Location Code", "LocCode", "Code"])
+            .ok_or_else(|| bad("no location code column"))?;
+        let local_col = column(&headers, &["Local Rate", "LocalRate"])
+            .ok_or_else(|| bad("no local rate column"))?;
+        let state_col = column(&headers, &["State Rate", "StateRate"])
+            .ok_or_else(|| bad("no state rate column"))?;
+        let combined_col = column(&headers, &["Combined Sales Tax", "Combined Rate", "Rate"]);
+        let effective_col = column(&headers, &["Effective Date", "Effective"]);
+
+        let file_period = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(period_from_file_name);
+
+        let mut loaded = 0;
+        for record in reader.records() {
+            let record = record?;
+            let field = |col: usize| record.get(col).unwrap_or("");
+
+            // Blank lines and footers
+            if field(code_col).is_empty() {
+                continue;
+            }
+            let loccode: i32 = field(code_col).parse().map_err(|_| bad("location code is not a number"))?;
+            let localrate: Decimal = field(local_col).parse().map_err(|_| bad("local rate is not a number"))?;
+            let staterate: Decimal = field(state_col).parse().map_err(|_| bad("state rate is not a number"))?;
+            let rate = match combined_col {
+                Some(col) => field(col).parse().map_err(|_| bad("combined rate is not a number"))?,
+                None => localrate + staterate,
+            };
+            let period = effective_col
+                .and_then(|col| field(col).parse::<Date>().ok())
+                .map(RatePeriod::of)
+                .or(file_period)
+                .ok_or_else(|| bad("no effective date column or period in the file name"))?;
+
+            let name = name_col.map(|col| field(col).to_string()).unwrap_or_default();
+            self.rates.insert(
+                (loccode, period),
+                OfflineRate { loccode, period, name, rate, localrate, staterate },
+            );
+            loaded += 1;
+        }
+
+        debug!("loaded {} rates from {}", loaded, path.display());
+        Ok(loaded)
+    }
+
+    pub fn get(&self, loccode: i32, period: RatePeriod) -> Option<&OfflineRate> {
+        self.rates.get(&(loccode, period))
+    }
+
+    /// Combined state and local rate
+    pub fn rate(&self, loccode: i32, period: RatePeriod) -> Option<Decimal> {
+        self.get(loccode, period).map(|r| r.rate)
+    }
+
+    pub fn localrate(&self, loccode: i32, period: RatePeriod) -> Option<Decimal> {
+        self.get(loccode, period).map(|r| r.localrate)
+    }
+
+    /// Jurisdiction name
+    pub fn name(&self, loccode: i32, period: RatePeriod) -> Option<&str> {
+        self.get(loccode, period).map(|r| r.name.as_str())
+    }
+
+    /// The most recent period loaded for a location code
+    pub fn latest(&self, loccode: i32) -> Option<&OfflineRate> {
+        self.rates
+            .values()
+            .filter(|r| r.loccode == loccode)
+            .max_by_key(|r| r.period)
+    }
+
+    /// Every rate loaded, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &OfflineRate> {
+        self.rates.values()
+    }
+
+    /// Every period with at least one rate loaded, oldest first
+    pub fn periods(&self) -> Vec<RatePeriod> {
+        let mut periods: Vec<RatePeriod> = self.rates.keys().map(|(_, p)| *p).collect();
+        periods.sort();
+        periods.dedup();
+        periods
+    }
+
+    pub fn len(&self) -> usize {
+        self.rates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+}
+
+/// Finds a period like `2020Q3` or `Q32020` in a name like `Rates_2020Q3`
+fn period_from_file_name(name: &str) -> Option<RatePeriod> {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter_map(|part| part.parse().ok())
+        .next()
+}
@@ -0,0 +1,181 @@
+//! Resolving a street address to tax info using only the loaded files.
+
+use super::{AddressRange, AddressTable, RateTable, Zip4Index};
+use crate::normalize::{split_house_number, split_zip, street_core};
+use crate::{Address, Code, Decimal, TaxInfo, TaxInfoError};
+use std::collections::HashMap;
+
+/// Resolves addresses offline, answering with the same `TaxInfo` (and the same errors) as
+/// [`crate::get`] does.
+///
+/// The `Code` describes how good the match was:
+/// - `AddrFound` when the street and house number match a range in the zip
+/// - `AdrrUpdatedAndFoundValidate` when the street only matches after ignoring directionals and
+///   suffixes, e.g. `45th` for `NE 45th St`
+/// - `AddrCorrectedAndFoundValidate` when the address is in a different zip than the one given,
+///   and every zip it's in has the same location code
+/// - `AddrNotFoundZipFound` when the address isn't found but the ZIP+4 is, which needs a
+///   [`Zip4Index`]. A ZIP+4 in more than one location code is treated as just its 5 digit zip.
+/// - `Zip5FoundNoAddrOrZip4` when only the 5 digit zip is known, the location code is the zip's
+///   most common one
+/// - `NoAddrNoZips`, as a `TaxInfoError::Dor`, when nothing matches
+#[derive(Clone, Debug)]
+pub struct Resolver {
+    addresses: AddressTable,
+    rates: RateTable,
+    zip4: Option<Zip4Index>,
+}
+
+impl Resolver {
+    pub fn new(addresses: AddressTable, rates: RateTable) -> Self {
+        Resolver { addresses, rates, zip4: None }
+    }
+
+    /// Also use the ZIP+4 file, for [`Resolver::resolve_zip`] and for when an address isn't found
+    pub fn with_zip4(mut self, zip4: Zip4Index) -> Self {
+        self.zip4 = Some(zip4);
+        self
+    }
+
+    /// Like [`crate::get`], but offline. The boundary files don't have cities, so `_city` is only
+    /// there to keep the signatures the same.
+    pub fn resolve(&self, addr: &str, _city: &str, zip: &str) -> Result<TaxInfo, TaxInfoError> {
+        let (house, street) = split_house_number(addr);
+        let split = split_zip(zip);
+        let zip5 = split.map(|(zip5, _)| zip5);
+        let in_zip = zip5.map(|zip5| self.addresses.in_zip(zip5)).unwrap_or(&[]);
+
+        if let Some(house) = house {
+            let exact = in_zip
+                .iter()
+                .find(|range| range.street() == street && range.contains_house(house));
+            if let Some(range) = exact {
+                return self.found(range, Code::AddrFound);
+            }
+
+            let core = street_core(&street);
+            let loose = in_zip
+                .iter()
+                .find(|range| range.street_core() == core && range.contains_house(house));
+            if let Some(range) = loose {
+                return self.found(range, Code::AdrrUpdatedAndFoundValidate);
+            }
+
+            // The same street name is in lots of towns, and the boundary files have no cities
+            // to tell them apart, so only correct the zip when every match agrees
+            let mut elsewhere: Vec<&AddressRange> = self.addresses.on_street(house, &street).collect();
+            elsewhere.sort_by_key(|range| range.address.zip);
+            if let Some(first) = elsewhere.first() {
+                if elsewhere.iter().all(|range| range.loccode == first.loccode) {
+                    return self.found(first, Code::AddrCorrectedAndFoundValidate);
+                }
+            }
+        }
+
+        // A ZIP+4 that straddles a boundary doesn't say which side the address is on, so then
+        // it's no better than the 5 digit zip
+        if let (Some((zip5, plus4)), Some(index)) = (split, &self.zip4) {
+            let found = index.lookup(zip5, plus4);
+            if let (Code::AddrNotFoundZipFound, [loccode]) = (found.code, found.loccodes.as_slice()) {
+                return self.tax_info(*loccode, Code::AddrNotFoundZipFound, None);
+            }
+        }
+
+        let in_zip5 = most_common_loccode(in_zip).or_else(|| {
+            let (zip5, index) = (zip5?, self.zip4.as_ref()?);
+            index.lookup(zip5, None).loccodes.first().copied()
+        });
+        match in_zip5 {
+            Some(loccode) => self.tax_info(loccode, Code::Zip5FoundNoAddrOrZip4, None),
+            None => Err(not_found(Code::NoAddrNoZips)),
+        }
+    }
+
+    /// Resolves a ZIP+4 (`98109-4607` or `981094607`) or a 5 digit zip, without an address.
+    ///
+    /// A ZIP+4 can straddle a boundary, so this answers with every candidate, in the order they
+    /// appear in the file. They all share the same `Code`, see [`Zip4Match`](super::Zip4Match). Needs a
+    /// [`Zip4Index`], without one every zip is `NoAddrNoZips`.
+    pub fn resolve_zip(&self, zip: &str) -> Result<Vec<TaxInfo>, TaxInfoError> {
+        let found = match (split_zip(zip), &self.zip4) {
+            (Some((zip5, plus4)), Some(index)) => index.lookup(zip5, plus4),
+            _ => return Err(not_found(Code::NoAddrNoZips)),
+        };
+        if found.loccodes.is_empty() {
+            return Err(not_found(found.code));
+        }
+        found
+            .loccodes
+            .iter()
+            .map(|loccode| self.tax_info(*loccode, found.code, None))
+            .collect()
+    }
+
+    pub fn addresses(&self) -> &AddressTable {
+        &self.addresses
+    }
+
+    pub fn rates(&self) -> &RateTable {
+        &self.rates
+    }
+
+    pub fn zip4(&self) -> Option<&Zip4Index> {
+        self.zip4.as_ref()
+    }
+
+    fn found(&self, range: &AddressRange, code: Code) -> Result<TaxInfo, TaxInfoError> {
+        self.tax_info(range.loccode, code, Some(range.address.clone()))
+    }
+
+    /// Fills in rates for the period in the address, or the latest period loaded
+    fn tax_info(
+        &self,
+        loccode: i32,
+        code: Code,
+        address: Option<Address>,
+    ) -> Result<TaxInfo, TaxInfoError> {
+        let period = address.as_ref().and_then(Address::rate_period);
+        let rate = period
+            .and_then(|p| self.rates.get(loccode, p))
+            .or_else(|| self.rates.latest(loccode))
+            .ok_or(TaxInfoError::Internal("no offline rate loaded for location code"))?;
+
+        Ok(TaxInfo {
+            loccode,
+            rate: rate.rate,
+            code,
+            localrate: rate.localrate,
+            debughint: None,
+            address,
+            taxrate: Some(rate.to_tax_rate()),
+        })
+    }
+}
+
+/// What DOR sends back when it can't find anything
+fn not_found(code: Code) -> TaxInfoError {
+    TaxInfoError::Dor((
+        code,
+        TaxInfo {
+            loccode: -1,
+            rate: Decimal::from(-1),
+            code,
+            localrate: Decimal::from(-1),
+            debughint: None,
+            address: None,
+            taxrate: None,
+        },
+    ))
+}
+
+fn most_common_loccode(ranges: &[AddressRange]) -> Option<i32> {
+    let mut counts: HashMap<i32, usize> = HashMap::new();
+    for range in ranges {
+        *counts.entry(range.loccode).or_insert(0) += 1;
+    }
+    // Ties go to the lower code, so the answer doesn't depend on HashMap order
+    counts
+        .into_iter()
+        .max_by_key(|(loccode, count)| (*count, -loccode))
+        .map(|(loccode, _)| loccode)
+}
@@ -0,0 +1,151 @@
+//! DOR's ZIP+4 boundary file, mapping ranges of ZIP+4s to location codes.
+
+use super::{column, csv_files, OfflineError};
+use crate::Code;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// One row of the ZIP+4 file, a range of plus 4s in a 5 digit zip
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Zip4Range {
+    pub zip: u32,
+    pub plus4_low: u32,
+    pub plus4_high: u32,
+    pub loccode: i32,
+}
+
+impl Zip4Range {
+    pub fn contains(&self, plus4: u32) -> bool {
+        self.plus4_low <= plus4 && plus4 <= self.plus4_high
+    }
+}
+
+/// What a ZIP+4 resolved to. A ZIP+4 can straddle a boundary, so there can be several codes.
+///
+/// `code` means what it does coming from DOR: `AddrNotFoundZipFound` when the ZIP+4 was found,
+/// `Zip5FoundNoAddrOrZip4` when only the 5 digit zip was, `NoAddrNoZips` when neither was.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Zip4Match {
+    pub code: Code,
+    /// Every candidate location code, in file order with duplicates removed. Empty when `code` is
+    /// `NoAddrNoZips`.
+    pub loccodes: Vec<i32>,
+}
+
+/// ZIP+4 ranges from DOR's boundary file, indexed by 5 digit zip.
+///
+/// The file is csv with a header row. Columns are found by name: the zip (`zip`, `Zip Code` or
+/// `Zip Code Low`), the start of the plus 4 range (`plus4`, `plus4low` or `Zip Ext Low`), the end
+/// of it (`plus4high` or `Zip Ext High`, the same as the start if missing) and the location code
+/// (`code`, `loccode` or `Location Code`).
+#[derive(Clone, Debug, Default)]
+pub struct Zip4Index {
+    by_zip: HashMap<u32, Vec<Zip4Range>>,
+}
+
+impl Zip4Index {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every csv file in a directory
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, OfflineError> {
+        let mut index = Self::new();
+        for file in csv_files(dir.as_ref())? {
+            index.load_file(file)?;
+        }
+        Ok(index)
+    }
+
+    /// Adds the ranges in one file. Returns how many rows were loaded.
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<usize, OfflineError> {
+        let path = path.as_ref();
+        let bad = |reason| OfflineError::Format(path.to_path_buf(), reason);
+
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_path(path)?;
+        let headers = reader.headers()?.clone();
+
+        let zip_col = column(&headers, &["zip", "Zip Code", "Zip Code Low", "Zip5"])
+            .ok_or_else(|| bad("no zip column"))?;
+        let low_col = column(&headers, &["plus4", "plus4low", "Zip Ext Low", "Plus 4"])
+            .ok_or_else(|| bad("no plus 4 column"))?;
+        let high_col = column(&headers, &["plus4high", "Zip Ext High"]);
+        let code_col = column(&headers, &["code", "loccode", "Location Code"])
+            .ok_or_else(|| bad("no location code column"))?;
+
+        let mut loaded = 0;
+        for record in reader.records() {
+            let record = record?;
+            let field = |col: usize| record.get(col).unwrap_or("");
+
+            // Blank lines and footers
+            if field(zip_col).is_empty() {
+                continue;
+            }
+            let zip: u32 = field(zip_col).parse().map_err(|_| bad("zip is not a number"))?;
+            let plus4_low: u32 = field(low_col).parse().map_err(|_| bad("plus 4 is not a number"))?;
+            let plus4_high: u32 = match high_col.map(field).filter(|v| !v.is_empty()) {
+                Some(v) => v.parse().map_err(|_| bad("plus 4 high is not a number"))?,
+                None => plus4_low,
+            };
+            let loccode: i32 = field(code_col).parse().map_err(|_| bad("location code is not a number"))?;
+
+            self.by_zip.entry(zip).or_insert_with(Vec::new).push(Zip4Range {
+                zip,
+                plus4_low,
+                plus4_high,
+                loccode,
+            });
+            loaded += 1;
+        }
+
+        debug!("loaded {} zip+4 ranges from {}", loaded, path.display());
+        Ok(loaded)
+    }
+
+    /// Every location code for a ZIP+4. Without a plus 4, or when the plus 4 isn't in the file,
+    /// every location code in the 5 digit zip.
+    pub fn lookup(&self, zip: u32, plus4: Option<u32>) -> Zip4Match {
+        let ranges = self.by_zip.get(&zip).map(|r| r.as_slice()).unwrap_or(&[]);
+
+        let in_plus4 = plus4
+            .map(|plus4| loccodes(ranges.iter().filter(|r| r.contains(plus4))))
+            .unwrap_or_default();
+        if !in_plus4.is_empty() {
+            return Zip4Match { code: Code::AddrNotFoundZipFound, loccodes: in_plus4 };
+        }
+
+        let in_zip = loccodes(ranges.iter());
+        if !in_zip.is_empty() {
+            return Zip4Match { code: Code::Zip5FoundNoAddrOrZip4, loccodes: in_zip };
+        }
+
+        Zip4Match { code: Code::NoAddrNoZips, loccodes: Vec::new() }
+    }
+
+    /// True when the 5 digit zip is in the file at all
+    pub fn contains_zip(&self, zip: u32) -> bool {
+        self.by_zip.contains_key(&zip)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_zip.values().map(|ranges| ranges.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_zip.is_empty()
+    }
+}
+
+fn loccodes<'a>(ranges: impl Iterator<Item = &'a Zip4Range>) -> Vec<i32> {
+    let mut codes = Vec::new();
+    for range in ranges {
+        if !codes.contains(&range.loccode) {
+            codes.push(range.loccode);
+        }
+    }
+    codes
+}
@@ -0,0 +1,255 @@
+//! Rate periods. DOR changes rates quarterly, so a period is a year and a quarter.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A calendar quarter that rates are valid for, e.g. `Q32020`, which runs from 2020-07-01 through
+/// 2020-09-30.
+///
+/// Periods sort oldest first.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct RatePeriod {
+    // Field order matters, the derived Ord sorts by year first
+    year: u16,
+    quarter: u8,
+}
+
+impl RatePeriod {
+    /// None unless quarter is 1 through 4
+    pub fn new(year: u16, quarter: u8) -> Option<Self> {
+        if (1..=4).contains(&quarter) {
+            Some(RatePeriod { year, quarter })
+        } else {
+            None
+        }
+    }
+
+    /// The period containing the given month (1 through 12)
+    pub fn containing(year: u16, month: u8) -> Option<Self> {
+        if (1..=12).contains(&month) {
+            Self::new(year, (month - 1) / 3 + 1)
+        } else {
+            None
+        }
+    }
+
+    /// The period a date falls in
+    pub fn of(date: Date) -> Self {
+        Self::containing(date.year, date.month).expect("Date months are 1-12")
+    }
+
+    /// The period we're in right now, according to the system clock (in UTC)
+    pub fn current() -> Self {
+        Self::of(Date::today())
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn quarter(&self) -> u8 {
+        self.quarter
+    }
+
+    /// The first day of the period
+    pub fn start_date(&self) -> Date {
+        Date { year: self.year, month: (self.quarter - 1) * 3 + 1, day: 1 }
+    }
+
+    /// The last day of the period, which is still in it
+    pub fn end_date(&self) -> Date {
+        let month = self.quarter * 3;
+        Date { year: self.year, month, day: days_in_month(self.year, month) }
+    }
+
+    /// True when the date falls in this period
+    pub fn contains(&self, date: Date) -> bool {
+        Self::of(date) == *self
+    }
+
+    /// Midnight UTC on the first day of the period. WA's rates change at midnight Pacific, so
+    /// this is a few hours early, which errs on the side of treating rates as stale.
+    pub fn starts_at(&self) -> SystemTime {
+        let start = self.start_date();
+        let days = days_from_civil(start.year as i64, start.month as u32, 1);
+        UNIX_EPOCH + Duration::from_secs(days.max(0) as u64 * 86_400)
+    }
+
+    /// When the next period starts, see [`RatePeriod::starts_at`]
+    pub fn ends_at(&self) -> SystemTime {
+        self.next().starts_at()
+    }
+
+    pub fn next(&self) -> Self {
+        if self.quarter == 4 {
+            RatePeriod { year: self.year + 1, quarter: 1 }
+        } else {
+            RatePeriod { year: self.year, quarter: self.quarter + 1 }
+        }
+    }
+
+    /// None for the first quarter of year 0, there's nothing before it
+    pub fn previous(&self) -> Option<Self> {
+        if self.quarter == 1 {
+            Some(RatePeriod { year: self.year.checked_sub(1)?, quarter: 4 })
+        } else {
+            Some(RatePeriod { year: self.year, quarter: self.quarter - 1 })
+        }
+    }
+}
+
+/// Formats like DOR does, `Q32020`
+impl fmt::Display for RatePeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Q{}{}", self.quarter, self.year)
+    }
+}
+
+/// Accepts DOR's `Q32020`, as well as `2020Q3` and `20Q3` which show up in DOR file names
+impl FromStr for RatePeriod {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_uppercase();
+        let (quarter, year) = if s.starts_with('Q') {
+            (s.get(1..2), s.get(2..))
+        } else {
+            let q = s.find('Q').ok_or("period has no quarter")?;
+            (s.get(q + 1..), s.get(..q))
+        };
+        let quarter: u8 = quarter
+            .and_then(|q| q.parse().ok())
+            .ok_or("period quarter is not a number")?;
+        let year = year.ok_or("period has no year")?;
+        let year: u16 = match (year.len(), year.parse::<u16>()) {
+            (2, Ok(y)) => 2000 + y,
+            (4, Ok(y)) if y > 0 => y,
+            (4, Ok(_)) => return Err("period year is 0"),
+            _ => return Err("period year is not a 2 or 4 digit number"),
+        };
+        Self::new(year, quarter).ok_or("period quarter is not 1-4")
+    }
+}
+
+/// A day on the calendar, for asking what the rate was on a given day
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Date {
+    // Field order matters, the derived Ord sorts by year, then month, then day
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    /// None unless it's a real day, `Date::new(2021, 2, 29)` is None
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        if (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day) {
+            Some(Date { year, month, day })
+        } else {
+            None
+        }
+    }
+
+    /// Today, according to the system clock (in UTC)
+    pub fn today() -> Self {
+        let days = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() / 86_400)
+            .unwrap_or(0);
+        let (year, month, day) = civil_from_days(days as i64);
+        Date { year: year as u16, month: month as u8, day: day as u8 }
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// `YYYYMMDD`, how DOR takes dates in its URL interface
+    pub(crate) fn to_dor_string(&self) -> String {
+        format!("{:04}{:02}{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// `2020-07-01`
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Accepts `2020-07-01`, `7/1/2020` and `20200701`
+impl FromStr for Date {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (year, month, day) = parse_ymd(s).ok_or("date is not YYYY-MM-DD, M/D/YYYY or YYYYMMDD")?;
+        Self::new(year, month, day).ok_or("date is not a real day")
+    }
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses the date formats DOR uses in its files, `7/1/2020`, `2020-07-01` and `20200701`, into
+/// (year, month, day)
+fn parse_ymd(s: &str) -> Option<(u16, u8, u8)> {
+    let s = s.trim();
+    let (year, month, day) = if s.contains('/') {
+        let mut parts = s.split('/');
+        let (m, d, y) = (parts.next()?, parts.next()?, parts.next()?);
+        (y, m, d)
+    } else if s.contains('-') {
+        let mut parts = s.split('-');
+        (parts.next()?, parts.next()?, parts.next()?)
+    } else if s.len() == 8 {
+        (s.get(..4)?, s.get(4..6)?, s.get(6..)?)
+    } else {
+        return None;
+    };
+    let (year, month, day) = (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?);
+    if (1..=12).contains(&month) && (1..=31).contains(&day) {
+        Some((year, month, day))
+    } else {
+        None
+    }
+}
+
+/// Days since 1970-01-01 to (year, month, day), from Howard Hinnant's `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = (if z >= 0 { z } else { z - 146_096 }) / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// (year, month, day) to days since 1970-01-01, from Howard Hinnant's `days_from_civil`
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let yoe = year - era * 400;
+    let mp = (if month > 2 { month - 3 } else { month + 9 }) as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
@@ -0,0 +1,118 @@
+//! Keeping request rates polite. DOR is a public service.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+// tokio's rather than std's, so it follows the clock when a test pauses it
+use tokio::time::Instant;
+
+/// A token bucket: `burst` requests can go right away, after that they're let through at
+/// `per_second`.
+///
+/// Clones share the same bucket, so hand clones to every [`crate::Client`] (or task) that should
+/// count against the same limit.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Can go negative, that's requests waiting on tokens that haven't been added yet
+    tokens: f64,
+    refilled: Instant,
+    stats: RateLimiterStats,
+}
+
+/// What a rate limiter has been up to
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct RateLimiterStats {
+    /// Tokens handed out
+    pub acquired: u64,
+    /// Of those, how many had to wait
+    pub delayed: u64,
+    /// All the waiting added up
+    pub total_wait: Duration,
+}
+
+impl RateLimiter {
+    /// # Panics
+    /// When `per_second` isn't positive, or `burst` is 0
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        assert!(per_second > 0.0, "per_second must be positive");
+        assert!(burst > 0, "burst must be at least 1");
+        RateLimiter {
+            per_second,
+            burst: burst as f64,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: burst as f64,
+                refilled: Instant::now(),
+                stats: RateLimiterStats::default(),
+            })),
+        }
+    }
+
+    pub fn per_second(&self) -> f64 {
+        self.per_second
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst as u32
+    }
+
+    /// Waits for a token, and says how long that took.
+    ///
+    /// Callers are served in the order they call, a token is reserved before waiting for it.
+    ///
+    /// # Panics
+    /// When it has to wait outside a tokio 0.2 runtime with the timer enabled
+    pub async fn acquire(&self) -> Duration {
+        let wait = self.reserve();
+        if wait > Duration::from_secs(0) {
+            tokio::time::delay_for(wait).await;
+        }
+        wait
+    }
+
+    /// Takes a token if there's one available right now
+    pub fn try_acquire(&self) -> bool {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.stats.acquired += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn stats(&self) -> RateLimiterStats {
+        self.bucket.lock().unwrap().stats
+    }
+
+    /// Takes a token, possibly one that won't exist for a while, and returns how long until it
+    /// does
+    fn reserve(&self) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        bucket.tokens -= 1.0;
+        bucket.stats.acquired += 1;
+        if bucket.tokens >= 0.0 {
+            return Duration::from_secs(0);
+        }
+
+        let wait = Duration::from_secs_f64(-bucket.tokens / self.per_second);
+        bucket.stats.delayed += 1;
+        bucket.stats.total_wait += wait;
+        wait
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.burst);
+        bucket.refilled = now;
+    }
+}
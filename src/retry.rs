@@ -0,0 +1,219 @@
+//! How hard to try before giving up on DOR.
+
+use crate::TaxInfoError;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+const MAX_ATTEMPTS: usize = 3;
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(7_000);
+const BASE_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+const JITTER: f64 = 0.5;
+
+/// When and how often to retry a lookup.
+///
+/// The wait before retry `n` is `base_backoff * 2^(n-1)`, capped at `max_backoff`, with up to
+/// `jitter` of it taken off at random so a pile of clients that failed together don't all come
+/// back together. Only errors where [`crate::TaxInfoError::retryable`] is true are retried.
+///
+/// ```
+/// use std::time::Duration;
+/// let policy = wataxrate::RetryPolicy::new()
+///     .max_attempts(5)
+///     .base_backoff(Duration::from_millis(500))
+///     .deadline(Duration::from_secs(20));
+/// let client = wataxrate::Client::builder().retry_policy(policy);
+/// ```
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    jitter: f64,
+    attempt_timeout: Duration,
+    deadline: Option<Duration>,
+}
+
+/// Why retrying stopped without an answer
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum StopReason {
+    /// Every attempt allowed was made
+    MaxAttempts,
+    /// The overall deadline came first
+    Deadline,
+}
+
+/// One failed attempt, as part of a [`RetryReport`]
+#[derive(Debug)]
+pub struct Attempt {
+    pub error: TaxInfoError,
+    /// When the attempt started, counting from the start of the first one
+    pub started: Duration,
+    /// How long the attempt took, not counting any wait for the rate limiter
+    pub took: Duration,
+}
+
+/// What happened on the way to giving up, the error in [`TaxInfoError::NoMoreRetries`]
+#[derive(Debug)]
+pub struct RetryReport {
+    pub reason: StopReason,
+    /// Every attempt, in order. Can be empty when the deadline passed before the first one.
+    pub attempts: Vec<Attempt>,
+    /// From the start of the first attempt to giving up
+    pub elapsed: Duration,
+}
+
+impl RetryReport {
+    pub fn last_error(&self) -> Option<&TaxInfoError> {
+        self.attempts.last().map(|attempt| &attempt.error)
+    }
+
+    /// How many attempts timed out
+    pub fn timeouts(&self) -> usize {
+        self.attempts
+            .iter()
+            .filter(|attempt| matches!(attempt.error, TaxInfoError::Timeout(_)))
+            .count()
+    }
+}
+
+impl fmt::Display for RetryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} after {} attempts in {:?}", self.reason, self.attempts.len(), self.elapsed)?;
+        if let Some(error) = self.last_error() {
+            write!(f, ", last error: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl RetryPolicy {
+    /// 3 attempts, backing off from 250ms up to 5s with half jitter, 7s per attempt and no
+    /// overall deadline
+    pub fn new() -> Self {
+        RetryPolicy {
+            max_attempts: MAX_ATTEMPTS,
+            base_backoff: BASE_BACKOFF,
+            max_backoff: MAX_BACKOFF,
+            jitter: JITTER,
+            attempt_timeout: DEFAULT_TIMEOUT,
+            deadline: None,
+        }
+    }
+
+    /// One attempt, nothing else
+    pub fn never() -> Self {
+        Self::new().max_attempts(1)
+    }
+
+    /// Attempts in total, including the first
+    ///
+    /// # Panics
+    /// When it's 0, there's always at least one attempt
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        assert!(max_attempts >= 1, "max_attempts must be at least 1");
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// The wait before the first retry, doubling after that
+    pub fn base_backoff(mut self, backoff: Duration) -> Self {
+        self.base_backoff = backoff;
+        self
+    }
+
+    /// The longest wait between attempts
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// How much of each wait can be taken off at random, 0 for none and 1 for anywhere between
+    /// no wait and the full backoff.
+    ///
+    /// # Panics
+    /// When it isn't between 0 and 1
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        assert!((0.0..=1.0).contains(&jitter), "jitter must be between 0 and 1");
+        self.jitter = jitter;
+        self
+    }
+
+    /// How long a single attempt may take
+    pub fn attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = timeout;
+        self
+    }
+
+    /// How long all the attempts and waits together may take. An attempt that would run past it
+    /// gets a shorter timeout, and a retry that would start after it doesn't happen.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub(crate) fn attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    /// The wait before retry `retry` (1 for the first retry), before jitter
+    pub fn backoff(&self, retry: usize) -> Duration {
+        if retry == 0 {
+            return Duration::from_secs(0);
+        }
+        let doublings = (retry - 1).min(31) as u32;
+        self.base_backoff
+            .checked_mul(1 << doublings)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// The wait before retry `retry`, with jitter
+    pub(crate) fn delay(&self, retry: usize) -> Duration {
+        let backoff = self.backoff(retry);
+        backoff - backoff.mul_f64(self.jitter * random_fraction())
+    }
+
+    /// The timeout for an attempt starting now, None when the deadline has already passed
+    pub(crate) fn timeout_from(&self, started: Instant) -> Option<Duration> {
+        match self.deadline {
+            None => Some(self.attempt_timeout),
+            Some(deadline) => deadline
+                .checked_sub(started.elapsed())
+                .filter(|left| *left > Duration::from_secs(0))
+                .map(|left| left.min(self.attempt_timeout)),
+        }
+    }
+
+    /// Whether waiting `delay` more still leaves time before the deadline
+    pub(crate) fn has_time_for(&self, started: Instant, delay: Duration) -> bool {
+        match self.deadline {
+            None => true,
+            Some(deadline) => started.elapsed() + delay < deadline,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::MaxAttempts => write!(f, "gave up"),
+            StopReason::Deadline => write!(f, "ran out of time"),
+        }
+    }
+}
+
+/// Between 0 and 1. `RandomState` is seeded randomly every time, which is plenty for jitter.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,68 @@
+//! Using a synchronous HTTP client from async code.
+
+use super::{Transport, TransportFuture, TransportResponse};
+use crate::TaxInfoError;
+use std::fmt;
+use std::sync::Arc;
+use url::Url;
+
+/// A synchronous transport. Blocking the async runtime with it would stall every other task, so
+/// hand it to a [`crate::Client`] wrapped in [`SpawnBlocking`].
+pub trait BlockingTransport: Send + Sync + 'static {
+    fn get(&self, url: Url) -> Result<TransportResponse, TaxInfoError>;
+}
+
+/// Any function from url to response works, handy for wrapping an HTTP client that isn't
+/// reqwest
+impl<F> BlockingTransport for F
+where
+    F: Fn(Url) -> Result<TransportResponse, TaxInfoError> + Send + Sync + 'static,
+{
+    fn get(&self, url: Url) -> Result<TransportResponse, TaxInfoError> {
+        self(url)
+    }
+}
+
+/// Runs a [`BlockingTransport`] on tokio's blocking thread pool
+///
+/// ```
+/// use wataxrate::transport::{SpawnBlocking, TransportResponse};
+/// let transport = SpawnBlocking::new(|url: url::Url| {
+///     // .. make the request with your own HTTP client ..
+///     Ok::<_, wataxrate::TaxInfoError>(TransportResponse::ok("<response ... />"))
+/// });
+/// let client = wataxrate::Client::builder().transport(transport);
+/// ```
+pub struct SpawnBlocking<T> {
+    inner: Arc<T>,
+}
+
+impl<T: BlockingTransport> SpawnBlocking<T> {
+    pub fn new(inner: T) -> Self {
+        SpawnBlocking { inner: Arc::new(inner) }
+    }
+}
+
+impl<T: BlockingTransport> Transport for SpawnBlocking<T> {
+    fn get(&self, url: Url) -> TransportFuture<'_> {
+        let inner = self.inner.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || inner.get(url))
+                .await
+                .map_err(|_| TaxInfoError::Internal("blocking transport panicked"))?
+        })
+    }
+}
+
+impl<T> Clone for SpawnBlocking<T> {
+    fn clone(&self) -> Self {
+        SpawnBlocking { inner: self.inner.clone() }
+    }
+}
+
+/// Closures can't be printed, so neither can this
+impl<T> fmt::Debug for SpawnBlocking<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpawnBlocking").finish()
+    }
+}
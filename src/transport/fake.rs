@@ -0,0 +1,117 @@
+//! Answers from memory, for tests.
+
+use super::{Transport, TransportFuture, TransportResponse};
+use crate::TaxInfoError;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use url::Url;
+
+/// Gives scripted answers in order, then the fallback once they run out, and remembers every url
+/// it was asked for.
+///
+/// ```
+/// use wataxrate::transport::FakeTransport;
+/// let transport = FakeTransport::new()
+///     .then_status(503, "busy")
+///     .then_hang()
+///     .otherwise_ok(r#"<response loccode="1726" ... />"#);
+/// ```
+///
+/// To check [`FakeTransport::requests`] after a client is done with it, hand the client an
+/// `Arc<FakeTransport>` and keep a clone.
+#[derive(Debug, Default)]
+pub struct FakeTransport {
+    script: Mutex<VecDeque<Answer>>,
+    fallback: Option<Answer>,
+    requests: Mutex<Vec<Url>>,
+}
+
+#[derive(Clone, Debug)]
+enum Answer {
+    Respond(TransportResponse),
+    /// Answers, but only once the delay is up
+    RespondAfter(Duration, TransportResponse),
+    Fail(String),
+    /// Never answers, so the attempt times out
+    Hang,
+}
+
+impl FakeTransport {
+    /// Nothing scripted, every request fails until something is
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Next, answer 200 with this body
+    pub fn then_ok(self, body: impl Into<String>) -> Self {
+        self.then(Answer::Respond(TransportResponse::ok(body)))
+    }
+
+    /// Next, answer 200 with this body once `delay` is up, so later requests can be answered
+    /// first
+    pub fn then_ok_after(self, delay: Duration, body: impl Into<String>) -> Self {
+        self.then(Answer::RespondAfter(delay, TransportResponse::ok(body)))
+    }
+
+    /// Next, answer with this status and body
+    pub fn then_status(self, status: u16, body: impl Into<String>) -> Self {
+        self.then(Answer::Respond(TransportResponse::new(status, body)))
+    }
+
+    /// Next, fail like the connection dropped
+    pub fn then_fail(self, reason: impl Into<String>) -> Self {
+        self.then(Answer::Fail(reason.into()))
+    }
+
+    /// Next, never answer
+    pub fn then_hang(self) -> Self {
+        self.then(Answer::Hang)
+    }
+
+    /// Once the script runs out, answer 200 with this body every time
+    pub fn otherwise_ok(mut self, body: impl Into<String>) -> Self {
+        self.fallback = Some(Answer::Respond(TransportResponse::ok(body)));
+        self
+    }
+
+    /// Once the script runs out, answer with this status and body every time
+    pub fn otherwise_status(mut self, status: u16, body: impl Into<String>) -> Self {
+        self.fallback = Some(Answer::Respond(TransportResponse::new(status, body)));
+        self
+    }
+
+    /// Every url asked for so far, oldest first
+    pub fn requests(&self) -> Vec<Url> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn then(self, answer: Answer) -> Self {
+        self.script.lock().unwrap().push_back(answer);
+        self
+    }
+
+    fn next(&self) -> Option<Answer> {
+        let scripted = self.script.lock().unwrap().pop_front();
+        scripted.or_else(|| self.fallback.clone())
+    }
+}
+
+impl Transport for FakeTransport {
+    fn get(&self, url: Url) -> TransportFuture<'_> {
+        self.requests.lock().unwrap().push(url);
+        let answer = self.next();
+        Box::pin(async move {
+            match answer {
+                Some(Answer::Respond(response)) => Ok(response),
+                Some(Answer::RespondAfter(delay, response)) => {
+                    tokio::time::delay_for(delay).await;
+                    Ok(response)
+                }
+                Some(Answer::Fail(reason)) => Err(TaxInfoError::Transport(reason.into())),
+                Some(Answer::Hang) => futures::future::pending().await,
+                None => Err(TaxInfoError::Transport("fake transport has nothing left to say".into())),
+            }
+        })
+    }
+}
@@ -0,0 +1,46 @@
+//! The default transport, reqwest's async client.
+
+use super::{Transport, TransportFuture, TransportResponse};
+use std::time::Duration;
+use url::Url;
+
+/// Sends requests with a pooled `reqwest::Client`. Cloning is cheap, the pool is shared.
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    http: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// # Panics
+    /// Like `reqwest::Client::new`, when the TLS backend can't be initialized
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses a client that's already set up, with whatever proxies, headers or TLS it has
+    pub fn from_client(http: reqwest::Client) -> Self {
+        ReqwestTransport { http }
+    }
+
+    /// A new client where establishing a connection may take at most `connect_timeout`
+    pub(crate) fn with_connect_timeout(
+        connect_timeout: Option<Duration>,
+    ) -> Result<Self, reqwest::Error> {
+        let mut http = reqwest::Client::builder();
+        if let Some(connect_timeout) = connect_timeout {
+            http = http.connect_timeout(connect_timeout);
+        }
+        Ok(ReqwestTransport { http: http.build()? })
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn get(&self, url: Url) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let response = self.http.get(url).send().await?;
+            let status = response.status().as_u16();
+            let body = response.text().await?;
+            Ok(TransportResponse { status, body })
+        })
+    }
+}
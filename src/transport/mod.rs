@@ -0,0 +1,68 @@
+//! Getting DOR's response body, whatever HTTP stack that takes.
+//!
+//! [`crate::Client`] uses a `ReqwestTransport`, from the default `reqwest` feature, unless it's
+//! given something else with [`crate::ClientBuilder::transport`]. A synchronous HTTP client can
+//! be plugged in with [`SpawnBlocking`], and [`FakeTransport`] answers from memory, for tests
+//! that shouldn't touch the network.
+//!
+//! Timeouts and backoff still use tokio's timer, so lookups that retry need a tokio runtime
+//! whichever transport is used.
+
+mod blocking;
+mod fake;
+#[cfg(feature = "reqwest")]
+mod http;
+
+pub use blocking::{BlockingTransport, SpawnBlocking};
+pub use fake::FakeTransport;
+#[cfg(feature = "reqwest")]
+pub use http::ReqwestTransport;
+
+use crate::TaxInfoError;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use url::Url;
+
+/// What [`Transport::get`] returns
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<TransportResponse, TaxInfoError>> + Send + 'a>>;
+
+/// Something that can GET a url. Shared between tasks, so it takes `&self`.
+///
+/// Failing to get any response at all should be a [`TaxInfoError::Transport`] (or
+/// `TaxInfoError::Http`, from reqwest). A response that isn't 2xx is still a response, the client turns it
+/// into a [`TaxInfoError::Status`].
+pub trait Transport: Debug + Send + Sync {
+    fn get(&self, url: Url) -> TransportFuture<'_>;
+}
+
+/// So a transport can be given to a client and still be looked at afterwards
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn get(&self, url: Url) -> TransportFuture<'_> {
+        (**self).get(url)
+    }
+}
+
+/// An HTTP response, however it was fetched
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TransportResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl TransportResponse {
+    pub fn new(status: u16, body: impl Into<String>) -> Self {
+        TransportResponse { status, body: body.into() }
+    }
+
+    /// A 200 with this body
+    pub fn ok(body: impl Into<String>) -> Self {
+        Self::new(200, body)
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status >= 200 && self.status < 300
+    }
+}
@@ -0,0 +1,190 @@
+mod common;
+
+use common::{builder, client, ms, temp_dir, SEATTLE};
+use futures::StreamExt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use wataxrate::batch::{CsvBatchError, CsvBatchSummary};
+use wataxrate::transport::{FakeTransport, Transport, TransportFuture};
+use wataxrate::{Code, RetryPolicy};
+
+const INPUT: &str = "\
+id,addr,city,zip
+1,400 Broad St,Seattle,98109
+2,500 Broad St,Seattle,98109
+3,1 Nowhere Ln,Seattle,98109
+";
+
+fn dor_rejects() -> String {
+    format!(
+        r#"<response loccode="-1" localrate="-1" rate="-1" code="{}" />"#,
+        Code::NoAddrNoZips.number()
+    )
+}
+
+/// A fresh directory with the input in it
+fn dir(test: &str) -> PathBuf {
+    let dir = temp_dir(&format!("batch-{}", test));
+    fs::write(dir.join("in.csv"), INPUT).unwrap();
+    dir
+}
+
+/// `SEATTLE` with a made up location code, to tell answers apart
+fn answer(loccode: i32) -> String {
+    SEATTLE.replace(r#"loccode="1726""#, &format!(r#"loccode="{}""#, loccode))
+}
+
+fn queries(count: usize) -> Vec<(String, String, String)> {
+    let query = |n| (format!("{}00 Broad St", n), "Seattle".to_string(), "98109".to_string());
+    (1..=count).map(query).collect()
+}
+
+/// Keeps track of how many requests are waiting on an answer at once
+#[derive(Debug)]
+struct InFlight {
+    inner: FakeTransport,
+    now: AtomicUsize,
+    most: AtomicUsize,
+}
+
+impl Transport for InFlight {
+    fn get(&self, url: url::Url) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let now = self.now.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most.fetch_max(now, Ordering::SeqCst);
+            let response = self.inner.get(url).await;
+            self.now.fetch_sub(1, Ordering::SeqCst);
+            response
+        })
+    }
+}
+
+async fn run(dir: &Path, transport: FakeTransport) -> Result<CsvBatchSummary, CsvBatchError> {
+    let client = builder(transport).retry_policy(RetryPolicy::never()).build().unwrap();
+    client.csv_batch(dir.join("in.csv"), dir.join("out.csv")).concurrency(1).run().await
+}
+
+#[tokio::test]
+async fn rerun_only_looks_up_what_is_left() {
+    let dir = dir("resume");
+
+    let first = FakeTransport::new()
+        .then_ok(SEATTLE)
+        .then_status(503, "busy")
+        .then_ok(dor_rejects());
+    let summary = run(&dir, first).await.unwrap();
+    let expected = CsvBatchSummary { total: 3, skipped: 0, succeeded: 1, rejected: 1, failed: 1 };
+    assert_eq!(summary, expected);
+
+    // Only the row that failed goes to DOR again, there's one answer for it
+    let summary = run(&dir, FakeTransport::new().then_ok(SEATTLE)).await.unwrap();
+    let expected = CsvBatchSummary { total: 3, skipped: 2, succeeded: 2, rejected: 1, failed: 0 };
+    assert_eq!(summary, expected);
+
+    let output = fs::read_to_string(dir.join("out.csv")).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "id,addr,city,zip,loccode,rate,localrate,code,normalized_addr,error");
+    assert_eq!(lines[1], "1,400 Broad St,Seattle,98109,1726,0.101,0.036,0,400 BROAD ST,");
+    assert_eq!(lines[2], "2,500 Broad St,Seattle,98109,1726,0.101,0.036,0,500 BROAD ST,");
+    assert!(lines[3].starts_with("3,1 Nowhere Ln,Seattle,98109,,,,"), "{}", lines[3]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn row_with_a_new_city_or_zip_is_looked_up_again() {
+    let dir = dir("changed");
+    let summary = run(&dir, FakeTransport::new().otherwise_ok(SEATTLE)).await.unwrap();
+    assert_eq!(summary.succeeded, 3);
+
+    // Same streets, but the first row moved to another zip and the second to another city
+    let changed = INPUT.replace("1,400 Broad St,Seattle,98109", "1,400 Broad St,Seattle,98121");
+    let changed = changed.replace("2,500 Broad St,Seattle", "2,500 Broad St,Tacoma");
+    fs::write(dir.join("in.csv"), changed).unwrap();
+
+    let summary = run(&dir, FakeTransport::new().otherwise_ok(SEATTLE)).await.unwrap();
+    assert_eq!((summary.skipped, summary.succeeded), (1, 3));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn missing_column_is_an_error() {
+    let dir = dir("missing");
+    let client = client(FakeTransport::new());
+    let error = client
+        .csv_batch(dir.join("in.csv"), dir.join("out.csv"))
+        .columns("street", "city", "zip")
+        .run()
+        .await
+        .unwrap_err();
+    assert_eq!(error.to_string(), r#"the input has no "street" column"#);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn answers_are_in_query_order_whatever_order_they_arrive_in() {
+    tokio::time::pause();
+    // The first query's answer arrives last
+    let transport = FakeTransport::new()
+        .then_ok_after(ms(30), answer(1))
+        .then_ok_after(ms(20), answer(2))
+        .then_ok_after(ms(10), dor_rejects())
+        .then_ok(answer(4));
+    let client = builder(transport).retry_policy(RetryPolicy::never()).build().unwrap();
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let seen = progress.clone();
+    let results = client
+        .batch(queries(4))
+        .on_progress(move |p| seen.lock().unwrap().push(p))
+        .run()
+        .await;
+
+    let loccodes: Vec<Option<i32>> =
+        results.iter().map(|r| r.as_ref().ok().map(|info| info.loccode)).collect();
+    assert_eq!(loccodes, [Some(1), Some(2), None, Some(4)]);
+
+    let progress = progress.lock().unwrap();
+    let counts: Vec<(usize, usize)> = progress.iter().map(|p| (p.completed, p.failed)).collect();
+    // In the order they finished: 4, the rejection, 2 then 1
+    assert_eq!(counts, [(1, 0), (2, 1), (3, 1), (4, 1)]);
+    assert!(progress.iter().all(|p| p.total == 4));
+}
+
+#[tokio::test]
+async fn stream_answers_as_lookups_finish() {
+    tokio::time::pause();
+    let transport = FakeTransport::new()
+        .then_ok_after(ms(30), answer(1))
+        .then_ok_after(ms(20), answer(2))
+        .then_ok_after(ms(10), answer(3));
+    let client = client(transport);
+
+    let finished: Vec<(usize, i32)> = client
+        .batch(queries(3))
+        .stream()
+        .map(|(index, result)| (index, result.unwrap().loccode))
+        .collect()
+        .await;
+    assert_eq!(finished, [(2, 3), (1, 2), (0, 1)]);
+}
+
+#[tokio::test]
+async fn no_more_than_concurrency_lookups_at_once() {
+    tokio::time::pause();
+    let mut fake = FakeTransport::new();
+    for loccode in 1..=6 {
+        fake = fake.then_ok_after(ms(10 * (7 - loccode as u64)), answer(loccode));
+    }
+    let transport =
+        Arc::new(InFlight { inner: fake, now: AtomicUsize::new(0), most: AtomicUsize::new(0) });
+    let client = client(transport.clone());
+
+    let results = client.batch(queries(6)).concurrency(2).run().await;
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(transport.most.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "blocking")]
+
+mod common;
+
+use common::SEATTLE;
+use std::sync::{Arc, Mutex};
+use wataxrate::blocking::Client;
+use wataxrate::transport::FakeTransport;
+use wataxrate::TaxInfoError;
+
+/// The blocking client doesn't have a builder of its own, so this can't come from `common`
+fn client(transport: FakeTransport) -> Client {
+    Client::from_async(common::client(transport)).unwrap()
+}
+
+#[test]
+fn get_blocks_until_dor_answers() {
+    let client = client(FakeTransport::new().then_status(503, "busy").then_ok(SEATTLE));
+
+    // Retried on the client's own runtime, outside of any async code
+    let info = client.get("400 Broad St", "Seattle", "98109").unwrap();
+    assert_eq!(info.loccode, 1726);
+    assert_eq!(info.rate.to_string(), "0.101");
+
+    match client.get_basic("400 Broad St", "Seattle", "98109") {
+        Err(TaxInfoError::Transport(_)) => {}
+        other => panic!("expected the fake to have nothing left, got {:?}", other),
+    }
+}
+
+#[test]
+fn batch_runs_every_lookup() {
+    let client = client(FakeTransport::new().then_status(404, "not here").otherwise_ok(SEATTLE));
+    let queries = vec![
+        ("400 Broad St", "Seattle", "98109"),
+        ("500 Broad St", "Seattle", "98109"),
+        ("600 Broad St", "Seattle", "98109"),
+    ];
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let seen = progress.clone();
+    let results = client
+        .batch(queries)
+        .concurrency(1)
+        .on_progress(move |p| seen.lock().unwrap().push((p.completed, p.failed)))
+        .run();
+
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_err());
+    assert!(results[1..].iter().all(|r| r.as_ref().unwrap().loccode == 1726));
+    assert_eq!(*progress.lock().unwrap(), [(1, 1), (2, 1), (3, 1)]);
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use common::{builder, space_needle_in, temp_path};
+use std::fs;
+use std::time::Duration;
+use wataxrate::cache::{Cache, FileCache, MemoryCache};
+use wataxrate::transport::FakeTransport;
+use wataxrate::{Client, Date, RatePeriod};
+
+fn cached(transport: FakeTransport, cache: impl Cache + 'static) -> Client {
+    builder(transport).cache(cache).build().unwrap()
+}
+
+#[tokio::test]
+async fn past_period_is_cached() {
+    let transport = FakeTransport::new().then_ok(space_needle_in("Q32020"));
+    let client = cached(transport, MemoryCache::new(10));
+    let date = Date::new(2020, 8, 1).unwrap();
+
+    let first = client.get_as_of("400 Broad St", "Seattle", "98109", date).await.unwrap();
+    // The fake has nothing left to say, so this has to come from the cache
+    let second = client.get_as_of("400 Broad St", "Seattle", "98109", date).await.unwrap();
+    assert_eq!(first, second);
+
+    let stats = client.cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.len), (1, 1, 1));
+}
+
+#[tokio::test]
+async fn answer_for_another_period_is_not_cached() {
+    // DOR ignored the date and sent this quarter's rates
+    let transport = FakeTransport::new().otherwise_ok(space_needle_in("Q42026"));
+    let client = cached(transport, MemoryCache::new(10));
+    let date = Date::new(2020, 8, 1).unwrap();
+
+    let info = client.get_as_of("400 Broad St", "Seattle", "98109", date).await.unwrap();
+    assert_eq!(info.effective_period().unwrap().to_string(), "Q42026");
+    client.get_as_of("400 Broad St", "Seattle", "98109", date).await.unwrap();
+
+    let stats = client.cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.len), (0, 2, 0));
+}
+
+#[tokio::test]
+async fn least_recently_used_is_evicted() {
+    let current = RatePeriod::current().to_string();
+    let transport = FakeTransport::new().otherwise_ok(space_needle_in(&current));
+    let client = cached(transport, MemoryCache::new(2));
+
+    client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+    client.get("500 Broad St", "Seattle", "98109").await.unwrap();
+    // 400 is now used more recently than 500, so 500 goes to make room for 600
+    client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+    client.get("600 Broad St", "Seattle", "98109").await.unwrap();
+
+    let stats = client.cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.evictions, stats.len), (1, 3, 1, 2));
+
+    client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+    client.get("500 Broad St", "Seattle", "98109").await.unwrap();
+    let stats = client.cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.evictions), (2, 4, 2));
+}
+
+#[tokio::test]
+async fn current_rates_expire_when_their_quarter_is_over() {
+    // DOR's answer was for a quarter that has since ended
+    let transport = FakeTransport::new().otherwise_ok(space_needle_in("Q32020"));
+    let client = cached(transport, MemoryCache::new(10));
+
+    client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+    client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+
+    let stats = client.cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.expirations, stats.len), (0, 2, 1, 1));
+}
+
+#[tokio::test]
+async fn ttl_expires_before_the_quarter_does() {
+    let current = RatePeriod::current().to_string();
+    let cache = MemoryCache::new(10).with_ttl(Duration::from_millis(50));
+    let client = cached(FakeTransport::new().otherwise_ok(space_needle_in(&current)), cache);
+
+    client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+    client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+    tokio::time::delay_for(Duration::from_millis(100)).await;
+    client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+
+    let stats = client.cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.expirations), (1, 2, 1));
+}
+
+#[tokio::test]
+async fn file_cache_skips_rows_it_cant_read() {
+    let path = temp_path("cache.csv");
+    let current = RatePeriod::current().to_string();
+
+    let info = {
+        let transport = FakeTransport::new().then_ok(space_needle_in(&current));
+        let client = cached(transport, FileCache::open(&path).unwrap());
+        client.get("400 Broad St", "Seattle", "98109").await.unwrap()
+    };
+
+    let contents = fs::read_to_string(&path).unwrap();
+    let good = contents.lines().nth(1).unwrap();
+    let bad_loccode = good.replacen(",1726,", ",not a number,", 1);
+    let half_written = &good[..good.len() / 2];
+    fs::write(&path, format!("{}not,a,cache,row\n{}\n{}", contents, bad_loccode, half_written))
+        .unwrap();
+
+    // Nothing scripted, so only the cache can answer
+    let cache = FileCache::open(&path).unwrap();
+    assert_eq!(cache.skipped(), 3);
+    let answer = cached(FakeTransport::new(), cache).get("400 Broad St", "Seattle", "98109").await;
+    assert_eq!(answer.unwrap(), info);
+
+    // Opening rewrote the file without them
+    assert_eq!(FileCache::open(&path).unwrap().skipped(), 0);
+    fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn file_cache_reloads_escaped_values_unchanged() {
+    let path = temp_path("cache-escaped.csv");
+    let current = RatePeriod::current().to_string();
+    let dor = space_needle_in(&current)
+        .replace("BROAD ST", "O&apos;BRIEN &amp; SONS RD")
+        .replace(r#"name="SEATTLE""#, r#"name="SEATTLE &amp; KING""#);
+
+    let info = {
+        let client = cached(FakeTransport::new().then_ok(dor), FileCache::open(&path).unwrap());
+        client.get("400 O'Brien & Sons Rd", "Seattle", "98109").await.unwrap()
+    };
+    assert_eq!(info.address.as_ref().unwrap().street.as_deref(), Some("O'BRIEN & SONS RD"));
+
+    // Nothing scripted, so the reopened cache has to answer with the same values
+    let client = cached(FakeTransport::new(), FileCache::open(&path).unwrap());
+    let cached = client.get("400 O'Brien & Sons Rd", "Seattle", "98109").await.unwrap();
+    assert_eq!(cached, info);
+
+    fs::remove_file(&path).unwrap();
+}
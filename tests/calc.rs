@@ -0,0 +1,112 @@
+use wataxrate::calc::{calculate, calculate_invoice, Rounding};
+use wataxrate::{Code, Decimal, TaxInfo, TaxRate};
+
+fn d(s: &str) -> Decimal {
+    s.parse().unwrap()
+}
+
+/// Seattle, 10.1%
+fn seattle() -> TaxInfo {
+    TaxInfo {
+        loccode: 1726,
+        rate: d("0.101"),
+        code: Code::AddrFound,
+        localrate: d("0.036"),
+        debughint: None,
+        address: None,
+        taxrate: Some(TaxRate {
+            name: "SEATTLE".to_string(),
+            code: "1726".to_string(),
+            localrate: d("0.036"),
+            staterate: d("0.065"),
+        }),
+    }
+}
+
+#[test]
+fn rounds_to_the_nearest_cent() {
+    let tax = calculate(&seattle(), d("19.99"));
+    assert_eq!(tax.taxable, d("19.99"));
+    assert_eq!(tax.total.to_string(), "2.02");
+    assert_eq!(tax.state.to_string(), "1.30");
+    assert_eq!(tax.local.to_string(), "0.72");
+}
+
+#[test]
+fn state_and_local_add_up_to_the_total() {
+    for cents in &[1, 5, 99, 1999, 12345, 100_000] {
+        let tax = calculate(&seattle(), Decimal::from_cents(*cents));
+        assert_eq!(tax.state + tax.local, tax.total, "{} cents", cents);
+    }
+}
+
+#[test]
+fn without_taxrate_the_state_rate_is_the_rest() {
+    let mut info = seattle();
+    info.taxrate = None;
+    assert_eq!(calculate(&info, d("19.99")), calculate(&seattle(), d("19.99")));
+}
+
+#[test]
+fn per_line_and_per_invoice_round_differently() {
+    let info = seattle();
+    let lines = vec![(&info, d("0.05")), (&info, d("0.05")), (&info, d("0.05"))];
+
+    // 0.00505 rounds up to a cent on every line
+    let per_line = calculate_invoice(lines.clone(), Rounding::PerLine);
+    assert_eq!(per_line.lines.len(), 3);
+    assert!(per_line.lines.iter().all(|line| line.total == d("0.01")));
+    assert_eq!(per_line.total.total, d("0.03"));
+    assert_eq!(per_line.total.state, d("0"));
+    assert_eq!(per_line.total.local, d("0.03"));
+    assert_eq!(per_line.total.taxable, d("0.15"));
+
+    // 0.01515 rounds once
+    let per_invoice = calculate_invoice(lines, Rounding::PerInvoice);
+    assert_eq!(per_invoice.lines[0].total, d("0.00505"));
+    assert_eq!(per_invoice.total.total, d("0.02"));
+    assert_eq!(per_invoice.total.state, d("0.01"));
+    assert_eq!(per_invoice.total.local, d("0.01"));
+}
+
+#[test]
+fn refunds_round_like_sales_the_other_way() {
+    let sale = calculate(&seattle(), d("19.99"));
+    let refund = calculate(&seattle(), d("-19.99"));
+    assert_eq!(refund.total, -sale.total);
+    assert_eq!(refund.state, -sale.state);
+    assert_eq!(refund.local, -sale.local);
+
+    // Half a cent rounds away from zero both ways
+    let half = calculate(&seattle(), d("0.05"));
+    let half_refund = calculate(&seattle(), d("-0.05"));
+    assert_eq!(half.total, d("0.01"));
+    assert_eq!(half_refund.total, d("-0.01"));
+}
+
+#[test]
+fn refunded_invoice_nets_to_zero() {
+    let info = seattle();
+    let lines = vec![(&info, d("19.99")), (&info, d("0.05")), (&info, d("-19.99")), (&info, d("-0.05"))];
+    for &rounding in &[Rounding::PerLine, Rounding::PerInvoice] {
+        let tax = calculate_invoice(lines.clone(), rounding);
+        assert!(tax.total.total.is_zero(), "{:?}", rounding);
+        assert!(tax.total.state.is_zero(), "{:?}", rounding);
+        assert!(tax.total.local.is_zero(), "{:?}", rounding);
+    }
+}
+
+#[test]
+fn lines_can_go_to_different_places() {
+    let seattle = seattle();
+    let mut spokane = seattle.clone();
+    spokane.rate = d("0.089");
+    spokane.localrate = d("0.024");
+    spokane.taxrate = None;
+
+    let tax = calculate_invoice(vec![(&seattle, d("100")), (&spokane, d("100"))], Rounding::PerLine);
+    assert_eq!(tax.lines[0].total, d("10.10"));
+    assert_eq!(tax.lines[1].total, d("8.90"));
+    assert_eq!(tax.total.total, d("19.00"));
+    assert_eq!(tax.total.state, d("13.00"));
+}
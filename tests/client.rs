@@ -0,0 +1,78 @@
+mod common;
+
+use common::{builder, SEATTLE};
+use std::sync::Arc;
+use wataxrate::transport::FakeTransport;
+use wataxrate::{Client, Date};
+
+/// A client that answers with `SEATTLE`, and the transport so its requests can be looked at
+fn client() -> (Client, Arc<FakeTransport>) {
+    let transport = Arc::new(FakeTransport::new().otherwise_ok(SEATTLE));
+    let client = builder(transport.clone())
+        .base_url("http://localhost:8080/AddressRates.aspx")
+        .build()
+        .unwrap();
+    (client, transport)
+}
+
+/// The query pairs of every request made, in order
+fn queries(transport: &FakeTransport) -> Vec<Vec<(String, String)>> {
+    transport
+        .requests()
+        .iter()
+        .map(|url| url.query_pairs().map(|(k, v)| (k.into_owned(), v.into_owned())).collect())
+        .collect()
+}
+
+fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+#[tokio::test]
+async fn address_goes_to_the_base_url() {
+    let (client, transport) = client();
+    client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].host_str(), Some("localhost"));
+    assert_eq!(requests[0].port(), Some(8080));
+    assert_eq!(requests[0].path(), "/AddressRates.aspx");
+    let expected = pairs(&[
+        ("output", "xml"),
+        ("addr", "400 Broad St"),
+        ("city", "Seattle"),
+        ("zip", "98109"),
+    ]);
+    assert_eq!(queries(&transport), vec![expected]);
+}
+
+#[tokio::test]
+async fn date_is_sent_as_dor_wants_it() {
+    let (client, transport) = client();
+    let date = Date::new(2020, 8, 1).unwrap();
+    client.get_as_of("400 Broad St", "Seattle", "98109", date).await.unwrap();
+    client.get_by_coords_as_of(47.62, -122.35, date).await.unwrap();
+
+    let expected = vec![
+        pairs(&[
+            ("output", "xml"),
+            ("addr", "400 Broad St"),
+            ("city", "Seattle"),
+            ("zip", "98109"),
+            ("date", "20200801"),
+        ]),
+        pairs(&[("output", "xml"), ("lat", "47.62"), ("lng", "-122.35"), ("date", "20200801")]),
+    ];
+    assert_eq!(queries(&transport), expected);
+}
+
+#[tokio::test]
+async fn coordinates_are_sent_as_lat_and_lng() {
+    let (client, transport) = client();
+    client.get_by_coords(47.62, -122.35).await.unwrap();
+    client.get_by_coords_basic(47.62, -122.35).await.unwrap();
+
+    let expected = pairs(&[("output", "xml"), ("lat", "47.62"), ("lng", "-122.35")]);
+    assert_eq!(queries(&transport), vec![expected.clone(), expected]);
+}
@@ -0,0 +1,48 @@
+//! Fixtures shared by the integration tests. Each test file uses only some of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use wataxrate::transport::Transport;
+use wataxrate::{Client, ClientBuilder};
+
+/// DOR's whole answer for the Space Needle, written the way DOR writes it
+pub const SPACE_NEEDLE: &str = r#"<response loccode="1726" localrate="0.036" rate="0.101" code="0" debughint="Address found"><addressline houselow="400" househigh="498" evenodd="E" street="BROAD ST" state="WA" zip="98109" plus4="4607" period="Q32020" code="1726" rta="Y" ptba="N" cez="N" /><rate name="SEATTLE" code="1726" staterate="0.065" localrate="0.036" /></response>"#;
+
+/// Just the rates for Seattle, with no address or rate elements
+pub const SEATTLE: &str = r#"<response loccode="1726" localrate="0.036" rate="0.101" code="0" />"#;
+
+/// `SPACE_NEEDLE`, but with rates for `period` rather than Q32020
+pub fn space_needle_in(period: &str) -> String {
+    SPACE_NEEDLE.replace(r#"period="Q32020""#, &format!(r#"period="{}""#, period))
+}
+
+pub fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+/// A client with everything but the transport left at its defaults
+pub fn client(transport: impl Transport + 'static) -> Client {
+    builder(transport).build().unwrap()
+}
+
+/// For a client that needs more than the transport set up
+pub fn builder(transport: impl Transport + 'static) -> ClientBuilder {
+    Client::builder().transport(transport)
+}
+
+/// A path in the temp directory that only this test run uses, with nothing at it yet
+pub fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("wataxrate-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+/// An empty directory at `temp_path(name)`
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = temp_path(name);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::{client, SEATTLE};
+use std::sync::Arc;
+use wataxrate::transport::FakeTransport;
+use wataxrate::{Code, Coordinates, TaxInfoError};
+
+#[test]
+fn bounding_box_includes_its_edges() {
+    let inside = [
+        (47.62, -122.35),
+        // The corners of the box
+        (45.54, -124.86),
+        (45.54, -116.91),
+        (49.01, -124.86),
+        (49.01, -116.91),
+    ];
+    for &(lat, lng) in inside.iter() {
+        assert!(Coordinates::new(lat, lng).in_washington(), "{}, {}", lat, lng);
+    }
+
+    let outside = [
+        // Portland, Vancouver BC and Boise
+        (45.52, -122.68),
+        (49.28, -123.12),
+        (43.62, -116.20),
+        // Just past each edge
+        (45.53, -120.0),
+        (49.02, -120.0),
+        (47.0, -124.87),
+        (47.0, -116.90),
+        // Swapped
+        (-122.35, 47.62),
+        (f64::NAN, -122.35),
+    ];
+    for &(lat, lng) in outside.iter() {
+        assert!(!Coordinates::new(lat, lng).in_washington(), "{}, {}", lat, lng);
+    }
+}
+
+#[tokio::test]
+async fn point_outside_wa_fails_before_any_request() {
+    let transport = Arc::new(FakeTransport::new().otherwise_ok(SEATTLE));
+    let client = client(transport.clone());
+
+    match client.get_by_coords(45.52, -122.68).await {
+        Err(TaxInfoError::OutsideWashington(coords)) => {
+            assert_eq!(coords, Coordinates::new(45.52, -122.68))
+        }
+        other => panic!("expected OutsideWashington, got {:?}", other),
+    }
+    assert!(client.get_by_coords_basic(49.28, -123.12).await.is_err());
+    assert!(transport.requests().is_empty());
+}
+
+#[tokio::test]
+async fn dor_code_7_is_invalid_long_lat() {
+    // Inside the box but out in the Pacific, DOR doesn't know it
+    let invalid = r#"<response loccode="-1" localrate="-1" rate="-1" code="7" />"#;
+    let transport = Arc::new(FakeTransport::new().otherwise_ok(invalid));
+    let client = client(transport.clone());
+
+    match client.get_by_coords(47.0, -124.8).await {
+        Err(TaxInfoError::InvalidLongLat(info)) => assert_eq!(info.code, Code::InvalidLongLat),
+        other => panic!("expected InvalidLongLat, got {:?}", other),
+    }
+    // Not retried, asking again won't change DOR's mind
+    assert_eq!(transport.requests().len(), 1);
+}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+use wataxrate::Decimal;
+
+fn d(s: &str) -> Decimal {
+    s.parse().unwrap()
+}
+
+#[test]
+fn parses_and_keeps_digits() {
+    assert_eq!(d("0.101").to_string(), "0.101");
+    assert_eq!(d("0.100").to_string(), "0.100");
+    assert_eq!(d("-1").to_string(), "-1");
+    assert_eq!(d(".5").to_string(), "0.5");
+    assert_eq!(d("+2.50").to_string(), "2.50");
+    assert_eq!(d(" 19.99 ").to_string(), "19.99");
+    assert_eq!(d("-0.065").to_string(), "-0.065");
+    assert_eq!(Decimal::new(101, 3), d("0.101"));
+    assert_eq!(Decimal::from_cents(1999).to_string(), "19.99");
+}
+
+#[test]
+fn rejects_what_is_not_a_decimal() {
+    for bad in &["", "-", ".", "1.2.3", "1e5", "0x10", "12a", "1,000"] {
+        assert!(bad.parse::<Decimal>().is_err(), "{:?} parsed", bad);
+    }
+    assert!("0.00000000000000000000000000001".parse::<Decimal>().is_err());
+    assert!("1000000000000000000000000000000000000000".parse::<Decimal>().is_err());
+}
+
+#[test]
+fn equal_values_compare_and_hash_equal() {
+    assert_eq!(d("0.1"), d("0.100"));
+    assert_eq!(d("-0"), d("0.00"));
+    let set: HashSet<Decimal> = vec![d("0.1"), d("0.10"), d("0.100")].into_iter().collect();
+    assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn orders_by_value() {
+    let mut values = [d("0.1"), d("-1.5"), d("0.0999"), d("2"), d("-1.25"), d("0")];
+    values.sort();
+    let sorted: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    assert_eq!(sorted, ["-1.5", "-1.25", "0", "0.0999", "0.1", "2"]);
+}
+
+#[test]
+fn comparing_far_apart_values_does_not_overflow() {
+    let big = d("10000000000000");
+    let tiny = d("0.0000000000000000000000000001");
+    assert_ne!(big, tiny);
+    assert!(big > tiny);
+    assert!(-big < tiny);
+    let huge = d("99999999999999999999999999999999999999");
+    assert!(huge > d("9999999999.9999999999999999999999999999"));
+    let mut values = vec![huge, tiny, -huge, big];
+    values.sort();
+    assert_eq!(values, vec![-huge, tiny, big, huge]);
+}
+
+#[test]
+fn rounds_half_away_from_zero() {
+    assert_eq!(d("2.01899").round_dp(2).to_string(), "2.02");
+    assert_eq!(d("2.005").round_dp(2).to_string(), "2.01");
+    assert_eq!(d("2.0049").round_dp(2).to_string(), "2.00");
+    assert_eq!(d("-2.005").round_dp(2).to_string(), "-2.01");
+    assert_eq!(d("-2.0049").round_dp(2).to_string(), "-2.00");
+    assert_eq!(d("1.5").round_dp(2).to_string(), "1.5");
+    assert_eq!(d("0.125").to_cents(), 13);
+    assert_eq!(d("-0.125").to_cents(), -13);
+    assert_eq!(d("7").to_cents(), 700);
+}
+
+#[test]
+fn arithmetic_is_exact() {
+    assert_eq!((d("0.101") * d("19.99")).to_string(), "2.01899");
+    assert_eq!((d("0.065") + d("0.036")).to_string(), "0.101");
+    assert_eq!((d("0.101") - d("0.036")).to_string(), "0.065");
+    assert_eq!((-d("1.50")).to_string(), "-1.50");
+    assert_eq!(d("0.1") + d("0.2"), d("0.3"));
+}
+
+#[test]
+fn multiplying_drops_trailing_zeros_instead_of_overflowing() {
+    let big = d("10000000000000.000000000000000000000000");
+    assert_eq!(big * d("20.000"), d("200000000000000"));
+}
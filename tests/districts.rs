@@ -0,0 +1,69 @@
+use wataxrate::{Address, SpecialDistricts, UnknownFlag};
+
+fn address(rta: Option<&str>, ptba: Option<&str>, cez: Option<&str>) -> Address {
+    Address {
+        houselow: Some(400),
+        househigh: Some(498),
+        evenodd: Some("E".to_string()),
+        street: Some("BROAD ST".to_string()),
+        state: Some("WA".to_string()),
+        zip: Some(98109),
+        plus4: Some(4607),
+        period: Some("Q32020".to_string()),
+        code: Some("1726".to_string()),
+        rta: rta.map(str::to_string),
+        ptba: ptba.map(str::to_string),
+        cez: cez.map(str::to_string),
+    }
+}
+
+#[test]
+fn reads_every_spelling_of_a_flag() {
+    let cases = [
+        ("Y", Some(true)),
+        ("y", Some(true)),
+        ("YES", Some(true)),
+        ("Yes", Some(true)),
+        ("TRUE", Some(true)),
+        ("true", Some(true)),
+        ("1", Some(true)),
+        (" Y ", Some(true)),
+        ("N", Some(false)),
+        ("n", Some(false)),
+        ("NO", Some(false)),
+        ("False", Some(false)),
+        ("0", Some(false)),
+        ("", None),
+        ("  ", None),
+    ];
+    for &(value, expected) in cases.iter() {
+        let address = address(Some(value), Some(value), Some(value));
+        assert_eq!(address.in_rta(), Ok(expected), "{:?}", value);
+        assert_eq!(address.in_ptba(), Ok(expected), "{:?}", value);
+        assert_eq!(address.in_cez(), Ok(expected), "{:?}", value);
+    }
+    assert_eq!(address(None, None, None).special_districts(), Ok(SpecialDistricts::default()));
+}
+
+#[test]
+fn refuses_flags_it_cant_read() {
+    for &value in ["X", "maybe", "2", "YN", "T", "F", "-1"].iter() {
+        let expected = UnknownFlag { attr: "ptba", value: value.to_string() };
+        assert_eq!(address(None, Some(value), None).in_ptba(), Err(expected), "{:?}", value);
+    }
+
+    // Trimmed, like the value that's read
+    let error = address(None, None, Some(" maybe ")).in_cez().unwrap_err();
+    assert_eq!(error, UnknownFlag { attr: "cez", value: "maybe".to_string() });
+    assert_eq!(error.to_string(), r#"DOR sent "maybe" for cez, expected Y or N"#);
+}
+
+#[test]
+fn special_districts_fails_on_the_first_bad_flag() {
+    let districts = address(Some("Y"), Some("N"), None).special_districts();
+    let expected = SpecialDistricts { rta: Some(true), ptba: Some(false), cez: None };
+    assert_eq!(districts, Ok(expected));
+
+    let error = address(Some("Y"), Some("?"), Some("!")).special_districts().unwrap_err();
+    assert_eq!(error.attr, "ptba");
+}
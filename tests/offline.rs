@@ -0,0 +1,182 @@
+mod common;
+
+use common::{temp_dir, temp_path};
+use std::error::Error;
+use std::fs;
+use wataxrate::offline::{AddressTable, OfflineError, RateTable, Resolver, Zip4Index};
+use wataxrate::{Code, LocationCode, Registry, TaxInfoError, TaxRate};
+
+const ADDRESSES: &str = "\
+houselow,househigh,evenodd,street,zip,period,code
+400,498,E,BROAD ST,98109,Q32020,1726
+400,498,E,MAIN ST,98101,Q32020,1726
+400,498,E,MAIN ST,99201,Q32020,3210
+100,198,E,RAINIER AVE,98118,Q32020,1726
+";
+
+const RATES: &str = "\
+Location,Location Code,Local Rate,State Rate,Combined Sales Tax
+SEATTLE,1726,0.036,0.065,0.101
+SPOKANE,3210,0.024,0.065,0.089
+";
+
+/// Loads the tables above from files in a fresh directory
+fn tables(test: &str) -> (AddressTable, RateTable) {
+    let dir = temp_dir(&format!("offline-{}", test));
+    fs::write(dir.join("addresses.csv"), ADDRESSES).unwrap();
+    fs::write(dir.join("Rates_2020Q3.csv"), RATES).unwrap();
+
+    let mut addresses = AddressTable::new();
+    addresses.load_file(dir.join("addresses.csv")).unwrap();
+    let mut rates = RateTable::new();
+    rates.load_file(dir.join("Rates_2020Q3.csv")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    (addresses, rates)
+}
+
+// 99201-0100 to 0199 straddles Seattle and Spokane, which no real ZIP+4 does
+const ZIP4: &str = "\
+zip,plus4low,plus4high,code
+98109,4600,4699,1726
+99201,0001,0099,3210
+99201,0100,0199,3210
+99201,0100,0199,1726
+";
+
+fn zip4_index(test: &str) -> Zip4Index {
+    let path = temp_path(&format!("zip4-{}.csv", test));
+    fs::write(&path, ZIP4).unwrap();
+    let mut index = Zip4Index::new();
+    assert_eq!(index.load_file(&path).unwrap(), 4);
+    fs::remove_file(&path).unwrap();
+    index
+}
+
+fn resolver(test: &str) -> Resolver {
+    let (addresses, rates) = tables(test);
+    Resolver::new(addresses, rates)
+}
+
+#[test]
+fn finds_address_in_its_zip() {
+    let info = resolver("found").resolve("400 Broad Street", "Seattle", "98109").unwrap();
+    assert_eq!(info.code, Code::AddrFound);
+    assert_eq!(info.loccode, 1726);
+    assert_eq!(info.rate.to_string(), "0.101");
+}
+
+#[test]
+fn corrects_zip_when_only_one_location_code_matches() {
+    let info = resolver("corrected").resolve("150 Rainier Ave", "Seattle", "98144").unwrap();
+    assert_eq!(info.code, Code::AddrCorrectedAndFoundValidate);
+    assert_eq!(info.loccode, 1726);
+    assert_eq!(info.address.unwrap().zip, Some(98118));
+}
+
+#[test]
+fn ambiguous_street_is_not_corrected() {
+    // 400 Main St is in both Seattle and Spokane
+    match resolver("ambiguous").resolve("400 Main St", "Seattle", "98144") {
+        Err(TaxInfoError::Dor((code, _))) => assert_eq!(code, Code::NoAddrNoZips),
+        other => panic!("expected NoAddrNoZips, got {:?}", other),
+    }
+}
+
+#[test]
+fn ambiguous_street_falls_back_to_the_zip() {
+    let info = resolver("zip").resolve("420 Main St", "Seattle", "98109").unwrap();
+    assert_eq!(info.code, Code::Zip5FoundNoAddrOrZip4);
+    assert_eq!(info.loccode, 1726);
+}
+
+#[test]
+fn zip4_in_one_location_code_is_found() {
+    let resolver = resolver("zip4").with_zip4(zip4_index("zip4"));
+    let info = resolver.resolve("1 Nowhere Ln", "Seattle", "98109-4607").unwrap();
+    assert_eq!(info.code, Code::AddrNotFoundZipFound);
+    assert_eq!(info.loccode, 1726);
+}
+
+#[test]
+fn zip4_straddling_a_boundary_falls_back_to_the_zip() {
+    let resolver = resolver("straddle").with_zip4(zip4_index("straddle"));
+    let info = resolver.resolve("1 Nowhere Ln", "Spokane", "99201-0150").unwrap();
+    assert_eq!(info.code, Code::Zip5FoundNoAddrOrZip4);
+    assert_eq!(info.loccode, 3210);
+
+    // Without the address table's help, the zip's first code in the ZIP+4 file
+    let (_, rates) = tables("straddle-rates");
+    let resolver = Resolver::new(AddressTable::new(), rates).with_zip4(zip4_index("straddle"));
+    let info = resolver.resolve("1 Nowhere Ln", "Spokane", "99201-0150").unwrap();
+    assert_eq!(info.code, Code::Zip5FoundNoAddrOrZip4);
+    assert_eq!(info.loccode, 3210);
+}
+
+#[test]
+fn resolve_zip_answers_with_every_candidate() {
+    let resolver = resolver("resolve-zip").with_zip4(zip4_index("resolve-zip"));
+    let codes = |zip: &str| -> Vec<(Code, i32)> {
+        let found = resolver.resolve_zip(zip).unwrap();
+        found.iter().map(|info| (info.code, info.loccode)).collect()
+    };
+
+    assert_eq!(codes("98109-4607"), vec![(Code::AddrNotFoundZipFound, 1726)]);
+    assert_eq!(codes("992010050"), vec![(Code::AddrNotFoundZipFound, 3210)]);
+    assert_eq!(
+        codes("99201-0150"),
+        vec![(Code::AddrNotFoundZipFound, 3210), (Code::AddrNotFoundZipFound, 1726)]
+    );
+    // A plus 4 that isn't in the file, or none at all, is every code in the zip
+    assert_eq!(codes("98109-9999"), vec![(Code::Zip5FoundNoAddrOrZip4, 1726)]);
+    assert_eq!(
+        codes("99201"),
+        vec![(Code::Zip5FoundNoAddrOrZip4, 3210), (Code::Zip5FoundNoAddrOrZip4, 1726)]
+    );
+
+    match resolver.resolve_zip("98001") {
+        Err(TaxInfoError::Dor((code, _))) => assert_eq!(code, Code::NoAddrNoZips),
+        other => panic!("expected NoAddrNoZips, got {:?}", other),
+    }
+}
+
+#[test]
+fn load_errors_say_what_went_wrong() {
+    let path = temp_path("offline-bad.csv");
+    fs::write(&path, "zip,code\n98109,1726\n").unwrap();
+    let error = Zip4Index::new().load_file(&path).unwrap_err();
+    assert_eq!(error.to_string(), format!("{}: no plus 4 column", path.display()));
+    assert!(error.source().is_none());
+    fs::remove_file(&path).unwrap();
+
+    // csv opens the file itself, so the io error comes wrapped in a csv one
+    let error = Zip4Index::new().load_file(&path).unwrap_err();
+    assert!(matches!(error, OfflineError::Csv(_)), "{:?}", error);
+    assert!(error.source().is_some());
+}
+
+#[test]
+fn registry_only_knows_names_from_dor() {
+    let seattle = TaxRate {
+        name: "SEATTLE".to_string(),
+        code: "1726".to_string(),
+        localrate: "0.036".parse().unwrap(),
+        staterate: "0.065".parse().unwrap(),
+    };
+    let unincorporated = TaxRate {
+        name: "KING CO UNINC".to_string(),
+        code: "1700".to_string(),
+        ..seattle.clone()
+    };
+
+    let empty = Registry::new();
+    assert!(empty.is_empty());
+    assert_eq!(empty.check(&seattle), None);
+    assert_eq!(empty.check(&unincorporated), None);
+
+    let registry = Registry::from_rate_table(&tables("registry").1);
+    assert_eq!(registry.name(LocationCode::new(1726).unwrap()), Some("SEATTLE"));
+    assert_eq!(registry.check(&seattle), Some(true));
+    let renamed = TaxRate { name: "Spokane".to_string(), ..seattle.clone() };
+    assert_eq!(registry.check(&renamed), Some(false));
+    assert_eq!(registry.check(&unincorporated), None);
+}
@@ -0,0 +1,75 @@
+use wataxrate::{Date, RatePeriod};
+
+fn period(year: u16, quarter: u8) -> RatePeriod {
+    RatePeriod::new(year, quarter).unwrap()
+}
+
+fn date(year: u16, month: u8, day: u8) -> Date {
+    Date::new(year, month, day).unwrap()
+}
+
+#[test]
+fn parses_every_way_dor_writes_periods() {
+    for s in &["Q32020", "q32020", " Q32020 ", "2020Q3", "20Q3"] {
+        assert_eq!(s.parse::<RatePeriod>(), Ok(period(2020, 3)), "{:?}", s);
+    }
+    assert_eq!(period(2020, 3).to_string(), "Q32020");
+}
+
+#[test]
+fn rejects_what_isnt_a_period() {
+    for s in &["", "Q", "Q52020", "Q02020", "QX2020", "Q3", "Q3202", "Q320200", "2020", "Q10000"] {
+        assert!(s.parse::<RatePeriod>().is_err(), "{:?}", s);
+    }
+    assert_eq!("Q10000".parse::<RatePeriod>(), Err("period year is 0"));
+}
+
+#[test]
+fn quarters_wrap_around_the_year() {
+    assert_eq!(period(2020, 4).next(), period(2021, 1));
+    assert_eq!(period(2020, 3).next(), period(2020, 4));
+    assert_eq!(period(2021, 1).previous(), Some(period(2020, 4)));
+    assert_eq!(period(2020, 4).previous(), Some(period(2020, 3)));
+    assert_eq!(period(0, 2).previous(), Some(period(0, 1)));
+    assert_eq!(period(0, 1).previous(), None);
+}
+
+#[test]
+fn end_date_is_the_last_day_of_the_quarter() {
+    assert_eq!(period(2020, 1).end_date(), date(2020, 3, 31));
+    assert_eq!(period(2020, 2).end_date(), date(2020, 6, 30));
+    assert_eq!(period(2020, 3).end_date(), date(2020, 9, 30));
+    assert_eq!(period(2020, 4).end_date(), date(2020, 12, 31));
+    assert_eq!(period(2020, 1).start_date(), date(2020, 1, 1));
+}
+
+#[test]
+fn contains_its_first_and_last_days_only() {
+    let q4 = period(2020, 4);
+    assert!(q4.contains(date(2020, 10, 1)));
+    assert!(q4.contains(date(2020, 12, 31)));
+    assert!(!q4.contains(date(2020, 9, 30)));
+    assert!(!q4.contains(date(2021, 1, 1)));
+    assert!(!q4.contains(date(2019, 12, 31)));
+}
+
+#[test]
+fn leap_days_are_real_days_in_leap_years_only() {
+    assert!(Date::new(2020, 2, 29).is_some());
+    assert!(Date::new(2000, 2, 29).is_some());
+    assert!(Date::new(2021, 2, 29).is_none());
+    assert!(Date::new(1900, 2, 29).is_none());
+    assert!(Date::new(2021, 4, 31).is_none());
+    assert!(Date::new(2021, 1, 0).is_none());
+    assert!(Date::new(2021, 13, 1).is_none());
+    assert!(period(2020, 1).contains(date(2020, 2, 29)));
+}
+
+#[test]
+fn dates_parse_in_dor_formats() {
+    for s in &["2020-02-29", "2/29/2020", "20200229"] {
+        assert_eq!(s.parse::<Date>(), Ok(date(2020, 2, 29)), "{:?}", s);
+    }
+    assert!("2021-02-29".parse::<Date>().is_err());
+    assert_eq!(date(2020, 7, 1).to_string(), "2020-07-01");
+}
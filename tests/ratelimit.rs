@@ -0,0 +1,82 @@
+mod common;
+
+use common::{builder, ms, SEATTLE};
+use std::time::Duration;
+use tokio::time::Instant;
+use wataxrate::transport::FakeTransport;
+use wataxrate::RateLimiter;
+
+#[tokio::test]
+async fn burst_goes_right_away() {
+    tokio::time::pause();
+    let limiter = RateLimiter::new(1.0, 3);
+    for _ in 0..3 {
+        assert_eq!(limiter.acquire().await, ms(0));
+    }
+    assert!(!limiter.try_acquire());
+    assert_eq!(limiter.stats().delayed, 0);
+}
+
+#[tokio::test]
+async fn waits_once_the_bucket_is_empty() {
+    tokio::time::pause();
+    let limiter = RateLimiter::new(10.0, 1);
+    limiter.acquire().await;
+
+    let start = Instant::now();
+    let waited = limiter.acquire().await;
+    assert_eq!(waited, ms(100));
+    assert!(start.elapsed() >= ms(100), "{:?}", start.elapsed());
+
+    // A second later the bucket is full again, but holds no more than the burst
+    tokio::time::advance(Duration::from_secs(1)).await;
+    assert!(limiter.try_acquire());
+    assert!(!limiter.try_acquire());
+}
+
+#[tokio::test]
+async fn waiters_queue_up() {
+    tokio::time::pause();
+    let limiter = RateLimiter::new(10.0, 1);
+    let waits = futures::future::join_all((0..3).map(|_| limiter.acquire())).await;
+    assert_eq!(waits, [ms(0), ms(100), ms(200)]);
+}
+
+#[test]
+fn clones_share_a_bucket() {
+    let limiter = RateLimiter::new(0.001, 2);
+    let clone = limiter.clone();
+    assert!(limiter.try_acquire());
+    assert!(clone.try_acquire());
+    assert!(!limiter.try_acquire());
+    assert!(!clone.try_acquire());
+    assert_eq!(limiter.stats().acquired, 2);
+    assert_eq!(clone.stats(), limiter.stats());
+}
+
+#[tokio::test]
+async fn stats_add_up_the_waiting() {
+    tokio::time::pause();
+    let limiter = RateLimiter::new(10.0, 1);
+    for _ in 0..3 {
+        limiter.acquire().await;
+    }
+    let stats = limiter.stats();
+    assert_eq!((stats.acquired, stats.delayed), (3, 2));
+    // The paused clock moves in whole milliseconds, so the second wait can be a hair shorter
+    assert!(ms(195) < stats.total_wait && stats.total_wait <= ms(200), "{:?}", stats.total_wait);
+}
+
+#[tokio::test]
+async fn client_waits_its_turn() {
+    tokio::time::pause();
+    let limiter = RateLimiter::new(10.0, 1);
+    let client = builder(FakeTransport::new().otherwise_ok(SEATTLE))
+        .rate_limiter(limiter.clone())
+        .build()
+        .unwrap();
+
+    client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+    client.get_basic("400 Broad St", "Seattle", "98109").await.unwrap();
+    assert_eq!((limiter.stats().acquired, limiter.stats().delayed), (2, 1));
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::{builder, ms, SEATTLE};
+use std::time::{Duration, Instant};
+use wataxrate::transport::FakeTransport;
+use wataxrate::{RetryPolicy, StopReason, TaxInfoError};
+
+#[test]
+fn backoff_doubles_up_to_the_max() {
+    let policy = RetryPolicy::new().base_backoff(ms(100)).max_backoff(ms(1_000));
+    let waits: Vec<Duration> = (0..7).map(|retry| policy.backoff(retry)).collect();
+    assert_eq!(waits, [ms(0), ms(100), ms(200), ms(400), ms(800), ms(1_000), ms(1_000)]);
+    assert_eq!(policy.backoff(usize::MAX), ms(1_000));
+}
+
+#[test]
+#[should_panic(expected = "max_attempts must be at least 1")]
+fn zero_attempts_is_refused() {
+    RetryPolicy::new().max_attempts(0);
+}
+
+#[tokio::test]
+async fn one_attempt_is_made_without_retries() {
+    let transport = FakeTransport::new().then_status(503, "busy");
+    let client = builder(transport).retry_policy(RetryPolicy::never()).build().unwrap();
+    match client.get("400 Broad St", "Seattle", "98109").await {
+        Err(TaxInfoError::NoMoreRetries(report)) => {
+            assert_eq!(report.reason, StopReason::MaxAttempts);
+            assert_eq!(report.attempts.len(), 1);
+        }
+        other => panic!("expected NoMoreRetries, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn deadline_cuts_retries_short() {
+    let policy = RetryPolicy::new()
+        .max_attempts(10)
+        .attempt_timeout(ms(100))
+        .base_backoff(ms(50))
+        .jitter(0.0)
+        .deadline(ms(300));
+    let transport = FakeTransport::new().otherwise_status(503, "busy").then_hang();
+    let client = builder(transport).retry_policy(policy).build().unwrap();
+
+    let started = Instant::now();
+    match client.get("400 Broad St", "Seattle", "98109").await {
+        Err(TaxInfoError::NoMoreRetries(report)) => {
+            assert_eq!(report.reason, StopReason::Deadline);
+            assert!(report.attempts.len() < 10, "{} attempts", report.attempts.len());
+            assert!(report.elapsed <= ms(400), "took {:?}", report.elapsed);
+        }
+        other => panic!("expected NoMoreRetries, got {:?}", other),
+    }
+    assert!(started.elapsed() < ms(1_000));
+}
+
+#[tokio::test]
+async fn attempt_running_into_the_deadline_gets_a_shorter_timeout() {
+    let policy = RetryPolicy::new().attempt_timeout(ms(5_000)).deadline(ms(200));
+    let client = builder(FakeTransport::new().then_hang()).retry_policy(policy).build().unwrap();
+
+    match client.get("400 Broad St", "Seattle", "98109").await {
+        Err(TaxInfoError::NoMoreRetries(report)) => {
+            assert_eq!(report.reason, StopReason::Deadline);
+            assert_eq!(report.attempts.len(), 1);
+            match report.attempts[0].error {
+                TaxInfoError::Timeout(timeout) => assert!(timeout <= ms(200), "{:?}", timeout),
+                ref other => panic!("expected a timeout, got {:?}", other),
+            }
+        }
+        other => panic!("expected NoMoreRetries, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn report_has_every_failed_attempt() {
+    let policy = RetryPolicy::new().max_attempts(3).attempt_timeout(ms(50)).base_backoff(ms(10));
+    let transport = FakeTransport::new()
+        .then_status(503, "busy")
+        .then_hang()
+        .then_fail("connection reset");
+    let client = builder(transport).retry_policy(policy).build().unwrap();
+
+    let error = client.get("400 Broad St", "Seattle", "98109").await.unwrap_err();
+    assert!(std::error::Error::source(&error).is_some());
+    let report = match error {
+        TaxInfoError::NoMoreRetries(report) => report,
+        other => panic!("expected NoMoreRetries, got {:?}", other),
+    };
+
+    assert_eq!(report.reason, StopReason::MaxAttempts);
+    assert_eq!(report.attempts.len(), 3);
+    assert!(matches!(report.attempts[0].error, TaxInfoError::Status { status: 503, .. }));
+    assert!(matches!(report.attempts[1].error, TaxInfoError::Timeout(_)));
+    assert!(matches!(report.attempts[2].error, TaxInfoError::Transport(_)));
+    assert_eq!(report.timeouts(), 1);
+    assert!(matches!(report.last_error(), Some(TaxInfoError::Transport(_))));
+
+    assert!(report.attempts[1].started >= report.attempts[0].started + report.attempts[0].took);
+    assert!(report.attempts[1].took >= ms(50));
+    assert!(report.elapsed >= report.attempts[2].started);
+    assert!(report.to_string().starts_with("gave up after 3 attempts"), "{}", report);
+}
+
+#[tokio::test]
+async fn errors_that_wont_go_away_are_not_retried() {
+    let transport = FakeTransport::new().then_status(404, "not here").otherwise_ok("<response");
+    let policy = RetryPolicy::new().base_backoff(ms(10));
+    let client = builder(transport).retry_policy(policy).build().unwrap();
+
+    match client.get("400 Broad St", "Seattle", "98109").await {
+        Err(TaxInfoError::Status { status: 404, body }) => assert_eq!(body, "not here"),
+        other => panic!("expected a 404, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn retries_until_dor_answers() {
+    let transport = FakeTransport::new()
+        .then_status(503, "busy")
+        .then_fail("connection reset")
+        .then_ok(SEATTLE);
+    let policy = RetryPolicy::new().base_backoff(ms(10));
+    let client = builder(transport).retry_policy(policy).build().unwrap();
+
+    let info = client.get("400 Broad St", "Seattle", "98109").await.unwrap();
+    assert_eq!(info.loccode, 1726);
+}
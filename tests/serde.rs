@@ -0,0 +1,69 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use common::SPACE_NEEDLE;
+use serde_json::json;
+use wataxrate::{Code, TaxInfo};
+
+#[test]
+fn taxinfo_json_shape() {
+    let info = TaxInfo::from_xml(SPACE_NEEDLE).unwrap();
+    let expected = json!({
+        "loccode": 1726,
+        "localrate": "0.036",
+        "rate": "0.101",
+        "code": {"name": "AddrFound", "value": 0},
+        "debughint": "Address found",
+        "address": {
+            "houselow": 400,
+            "househigh": 498,
+            "evenodd": "E",
+            "street": "BROAD ST",
+            "state": "WA",
+            "zip": 98109,
+            "plus4": 4607,
+            "period": "Q32020",
+            "code": "1726",
+            "rta": "Y",
+            "ptba": "N",
+            "cez": "N"
+        },
+        "taxrate": {
+            "name": "SEATTLE",
+            "code": "1726",
+            "staterate": "0.065",
+            "localrate": "0.036"
+        }
+    });
+    assert_eq!(serde_json::to_value(&info).unwrap(), expected);
+    assert_eq!(serde_json::from_value::<TaxInfo>(expected).unwrap(), info);
+}
+
+#[test]
+fn rates_keep_their_digits() {
+    let mut info = TaxInfo::from_xml(SPACE_NEEDLE).unwrap();
+    info.rate = "0.1000".parse().unwrap();
+    let json = serde_json::to_string(&info).unwrap();
+    assert!(json.contains(r#""rate":"0.1000""#), "{}", json);
+    assert_eq!(serde_json::from_str::<TaxInfo>(&json).unwrap().rate.to_string(), "0.1000");
+}
+
+#[test]
+fn code_name_has_to_match_its_value() {
+    let code: Code = serde_json::from_value(json!({"name": "NoAddrNoZips", "value": 6})).unwrap();
+    assert_eq!(code, Code::NoAddrNoZips);
+    assert!(serde_json::from_value::<Code>(json!({"name": "AddrFound", "value": 6})).is_err());
+    assert!(serde_json::from_value::<Code>(json!({"name": "Nope", "value": 8})).is_err());
+}
+
+#[test]
+fn missing_parts_are_null() {
+    let info = TaxInfo::from_xml(r#"<response loccode="-1" localrate="-1" rate="-1" code="6" />"#)
+        .unwrap();
+    let value = serde_json::to_value(&info).unwrap();
+    assert_eq!(value["debughint"], json!(null));
+    assert_eq!(value["address"], json!(null));
+    assert_eq!(value["taxrate"], json!(null));
+    assert_eq!(value["rate"], json!("-1"));
+}
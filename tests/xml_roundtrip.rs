@@ -0,0 +1,134 @@
+mod common;
+
+use common::SPACE_NEEDLE;
+use std::convert::TryFrom;
+use wataxrate::{Address, Code, TaxInfo, TaxRate};
+
+const ALL_CODES: [(Code, u8); 9] = [
+    (Code::AddrFound, 0),
+    (Code::AddrNotFoundZipFound, 1),
+    (Code::AdrrUpdatedAndFoundValidate, 2),
+    (Code::AddrUpdatedAndZipFoundValidate, 3),
+    (Code::AddrCorrectedAndFoundValidate, 4),
+    (Code::Zip5FoundNoAddrOrZip4, 5),
+    (Code::NoAddrNoZips, 6),
+    (Code::InvalidLongLat, 7),
+    (Code::InternalError, 9),
+];
+
+fn info(code: Code) -> TaxInfo {
+    TaxInfo {
+        loccode: 1726,
+        rate: "0.101".parse().unwrap(),
+        code,
+        localrate: "0.036".parse().unwrap(),
+        debughint: Some("Address found".to_string()),
+        address: Some(Address {
+            houselow: Some(400),
+            househigh: Some(498),
+            evenodd: Some("E".to_string()),
+            street: Some("BROAD ST".to_string()),
+            state: Some("WA".to_string()),
+            zip: Some(98109),
+            plus4: Some(4607),
+            period: Some("Q32020".to_string()),
+            code: Some("1726".to_string()),
+            rta: Some("Y".to_string()),
+            ptba: Some("N".to_string()),
+            cez: Some("N".to_string()),
+        }),
+        taxrate: Some(TaxRate {
+            name: "SEATTLE".to_string(),
+            code: "1726".to_string(),
+            localrate: "0.036".parse().unwrap(),
+            staterate: "0.065".parse().unwrap(),
+        }),
+    }
+}
+
+#[test]
+fn code_converts_both_ways() {
+    for &(code, value) in ALL_CODES.iter() {
+        assert_eq!(u8::from(code), value);
+        assert_eq!(Code::try_from(value), Ok(code));
+        assert_eq!(code.to_string(), value.to_string());
+        assert_eq!(code.to_string().parse::<Code>(), Ok(code));
+    }
+    assert!(Code::try_from(8).is_err());
+}
+
+#[test]
+fn every_code_roundtrips() {
+    for &(code, _) in ALL_CODES.iter() {
+        let info = info(code);
+        let xml = info.to_xml();
+        assert_eq!(TaxInfo::from_xml(&xml).unwrap(), info, "{}", xml);
+    }
+}
+
+#[test]
+fn code_is_written_as_dor_number() {
+    for &(code, value) in ALL_CODES.iter() {
+        let xml = info(code).to_xml();
+        assert!(xml.contains(&format!(r#"code="{}""#, value)), "{}", xml);
+    }
+}
+
+#[test]
+fn error_response_roundtrips() {
+    let info = TaxInfo {
+        loccode: -1,
+        rate: "-1".parse().unwrap(),
+        code: Code::NoAddrNoZips,
+        localrate: "-1".parse().unwrap(),
+        debughint: None,
+        address: None,
+        taxrate: None,
+    };
+    let xml = info.to_xml();
+    assert_eq!(TaxInfo::from_xml(&xml).unwrap(), info);
+}
+
+#[test]
+fn dor_response_roundtrips() {
+    let info = TaxInfo::from_xml(SPACE_NEEDLE).unwrap();
+    assert_eq!(info.code, Code::AddrFound);
+    assert_eq!(info.rate.to_string(), "0.101");
+
+    assert_eq!(info.address.as_ref().unwrap().state.as_deref(), Some("WA"));
+    assert_eq!(info.address.as_ref().unwrap().code.as_deref(), Some("1726"));
+
+    // Written back out byte for byte the way DOR sent it
+    assert_eq!(info.to_xml(), SPACE_NEEDLE);
+}
+
+#[test]
+fn escaped_values_roundtrip() {
+    let xml = SPACE_NEEDLE
+        .replace("BROAD ST", "O&apos;BRIEN &amp; SONS RD")
+        .replace(r#"name="SEATTLE""#, r#"name="SEATTLE &amp; KING""#);
+    let info = TaxInfo::from_xml(&xml).unwrap();
+    assert_eq!(info.address.as_ref().unwrap().street.as_deref(), Some("O'BRIEN & SONS RD"));
+    assert_eq!(info.taxrate.as_ref().unwrap().name, "SEATTLE & KING");
+
+    assert_eq!(info.to_xml(), xml);
+}
+
+#[test]
+fn built_response_is_written_like_dor_writes_it() {
+    assert_eq!(info(Code::AddrFound).to_xml(), SPACE_NEEDLE);
+}
+
+#[test]
+fn rates_keep_their_digits() {
+    let mut info = info(Code::AddrFound);
+    info.rate = "0.1000".parse().unwrap();
+    let xml = info.to_xml();
+    assert!(xml.contains(r#"rate="0.1000""#), "{}", xml);
+}
+
+#[test]
+fn not_xml_is_an_error() {
+    assert!(TaxInfo::from_xml("Service Unavailable").is_err());
+    assert!(TaxInfo::from_xml("<html><body>down</body></html>").is_err());
+}